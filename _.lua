@@ -5,6 +5,31 @@
 ---@diagnostic disable-next-line: lowercase-global, missing-return
 function lazy(args) end
 
+--- builds children with a function, and builds them again when a `Value` read by it changes
+---@param builder fun(ui: ui): nil
+---@diagnostic disable-next-line: lowercase-global
+function reactive(builder) end
+
+--- what a script returns, either a `view` function or a table of these hooks
+---@class (exact) Hooks
+--- the version of the script, given to the next script's `on_reload`
+---@field version integer?
+--- builds the ui
+---@field view fun(ui: ui, data: any?): nil
+--- called once, before `view`, the first time the script loads
+---@field init (fun(data: any?): nil)?
+--- called every frame with the time since the last frame, in seconds
+---@field on_frame (fun(dt: number): nil)?
+--- called with the size of the screen when it changes, and after the script loads
+---@field on_resize (fun(width: integer, height: integer): nil)?
+--- called before the script is replaced, what it returns is given to the next script's `on_reload`
+---@field save (fun(): any)?
+--- called instead of `init` when the script is reloaded, with what the previous script's `save`
+--- returned and its `version`. if `save` failed, the state is nil
+---@field on_reload (fun(state: any, version: integer?): nil)?
+--- called when the application exits
+---@field on_exit (fun(): nil)?
+
 --- An async runtime
 ---@class (exact) Runtime
 --- sleeps for a specific duration
@@ -13,7 +38,23 @@ function lazy(args) end
 --- 
 --- this returns an id that you can use to stop the task
 ---@field spawn fun(task: (fun(): nil) | thread): integer
---- attempts to stop a running task
+--- gets the status of a task, or nil if the task is unknown
+--- 
+--- tasks are forgotten once they're joined, or once there are many newer ones
+---@field status fun(id: integer): "running"|"finished"|"failed"|"stopped"|nil
+--- waits for a task to complete, returning what it returned
+--- 
+--- this raises the error of the task if it failed or was stopped
+---@field join fun(id: integer): any
+--- calls a function every `dur`, between frames
+--- 
+--- this returns an id that you can use to stop the timer
+---@field interval fun(dur: Duration, task: fun(): nil): integer
+--- calls a function once after `dur`, between frames
+--- 
+--- this returns an id that you can use to stop the timer
+---@field after fun(dur: Duration, task: fun(): nil): integer
+--- attempts to stop a running task, a timer or a `Value` subscription
 ---@field stop fun(integer): boolean
 Runtime = { }
 
@@ -27,6 +68,49 @@ Runtime = { }
 ---@field from_micros fun(micros: integer): Duration
 Duration = { }
 
+--- A named channel between lua and the host
+---@class (exact) Channel
+--- opens a channel that the host created
+---@field open fun(name: string): Channel
+--- waits for a value sent by the host
+---@field recv fun(self: Channel): integer|number|boolean|string|table|nil
+--- gets a value sent by the host, if one is available
+---@field try_recv fun(self: Channel): integer|number|boolean|string|table|nil
+--- sends a value to the host
+---@field send fun(self: Channel, value: Value|integer|number|boolean|string|table): boolean
+--- gets the name of the channel
+---@field name fun(self: Channel): string
+Channel = { }
+
+--- Runs local processes
+---@class (exact) Process
+--- spawns a process
+--- 
+--- `stdout` and `stderr` receive each line the process writes
+--- `exit` is called with the exit code of the process
+--- 
+--- this returns a task id, stopping the task with `Runtime.stop` kills the process
+---@field spawn fun(params: { command: string, args: string[]?, cwd: string?, env: table<string, string>?, stdout: (fun(line: string): nil)|Channel?, stderr: (fun(line: string): nil)|Channel?, exit: (fun(code: integer?): nil)? }): integer
+Process = { }
+
+--- Async filesystem access
+---@class (exact) Fs
+--- reads the contents of a file
+---@field read fun(path: string): string
+--- writes data to a file, replacing its contents
+---@field write fun(path: string, data: string): nil
+--- lists the names of the entries in a directory
+---@field list fun(path: string): string[]
+--- gets the metadata of a path
+--- 
+--- `modified` is in seconds since the unix epoch
+---@field metadata fun(path: string): { is_file: boolean, is_dir: boolean, len: integer, modified: number? }
+--- calls a function, between frames, when a file or anything in a directory changes
+--- 
+--- this returns an id that you can use to stop watching with `Runtime.stop`
+---@field watch fun(path: string, task: fun(path: string): nil): integer
+Fs = { }
+
 ---@enum Palette
 Palette = {
     --- The background color
@@ -66,15 +150,50 @@ Palette = {
 Color = { }
 
 --- A shared value between lua and rust
+--- 
+--- lists and maps are indexed like tables. unlike a table, setting a list item
+--- to nil removes it and shifts the items after it down, so a list has no holes
 ---@class (exact) Value
 --- create a new value
----@field new fun(value: integer|number|boolean|string): Value
+---@field new fun(value: integer|number|boolean|string|table): Value
+--- create a new list value
+---@field list fun(items: any[]?): Value
+--- create a new map value
+---@field map fun(items: table<string, any>?): Value
+--- create a value computed by a function
+--- 
+--- this is computed again when a value read by the function changes,
+--- before the next frame is drawn. reading it right after changing
+--- one of those values, in the same handler, gives the previous result
+---@field computed fun(compute: fun(): integer|number|boolean|string|table): Value
 --- create a new value, persisted and accessible via `id`
----@field persist fun(id: string, value: integer|number|boolean|string): Value
+---@field persist fun(id: string, value: integer|number|boolean|string|table): Value
 --- destroys a persisted value `id`, if it exists
 ---@field destroy fun(id: string): boolean
+--- get an item of a list, or an entry of a map
+--- 
+--- this is the same as indexing it, but works for keys that are also methods
+---@field get fun(self: Value, key: integer|string): any
+--- push an item to a list, insert an item into a list at an index,
+--- or insert an entry into a map
+---@field insert fun(self: Value, key: any, value: any?): nil
+--- remove an item from a list or an entry from a map, returning it
+---@field remove fun(self: Value, key: integer|string): any
+--- flips a boolean value, returning the new value
+---@field toggle fun(self: Value): boolean
+--- adds `step` (or 1) to a number value, returning the new value
+--- 
+--- integer values saturate instead of overflowing, like the arithmetic operators
+---@field inc fun(self: Value, step: number?): number
+--- clamps a number value between `min` and `max`, returning the new value
+---@field clamp fun(self: Value, min: number, max: number): number
+--- calls a function after the frame in which the value changed
+--- 
+--- this returns an id that you can use to stop the subscription,
+--- subscriptions are also stopped when the script is reloaded
+---@field on_change fun(self: Value, callback: fun(old: integer|number|boolean|string|table, new: integer|number|boolean|string|table): nil): integer
 --- get the inner value
----@field value integer|number|boolean|string
+---@field value integer|number|boolean|string|table
 Value = { }
 
 ---@enum Aligned
@@ -296,6 +415,15 @@ Toggle = {
     small_square = Toggle,
 }
 
+--- integer arithmetic saturates instead of overflowing, like `Value:inc`
+---@class Value
+---@operator add(number|Value): number
+---@operator sub(number|Value): number
+---@operator mul(number|Value): number
+---@operator div(number|Value): number
+---@operator unm: number
+---@operator concat(string|number|Value): string
+
 ---@class (exact) AlignedParams
 --- Alignment for its children
 ---@field align Aligned
@@ -419,7 +547,7 @@ ListParams = { }
 --- The class of the label
 ---@field class Label?
 --- The text of the label
----@field text string
+---@field text string|Value
 LabelParams = { }
 
 ---@class (exact) LabelStyle
@@ -638,7 +766,7 @@ UnconstrainedParams = { }
 ---  Horizontal layout of children
 ---@field horizontal fun(args: ListParams): nil
 ---  Label displays some text
----@field label fun(args: string|LabelParams): nil
+---@field label fun(args: string|Value|LabelParams): nil
 ---  Margin applies padding to a view
 ---@field margin fun(args: MarginParams): nil
 ---  A progress bar
@@ -18,7 +18,7 @@ fn main() -> std::io::Result<()> {
                 debug_anchor: too::layout::Anchor2::LEFT_TOP,
                 ..too::RunConfig::default()
             })
-            .watch_timeout(std::time::Duration::from_secs(1))
+            .watch_timeout(std::time::Duration::from_millis(50))
             .run()
             .await
     })
@@ -13,31 +13,63 @@ use too::{
 };
 
 use crate::{
-    Bindings, Context, Errors, Mapping, Notification, Notifications, Script, Tree,
+    Bindings, Channel, Context, Errors, FileLoader, Loader, Mapping, MemoryLoader, Notification,
+    Notifications, Sandbox, Script, Tree, TreeDiff, TreeDump,
+    bindings::Value,
+    budget::Budget,
+    channel::Channels,
+    errors::Reported,
+    reactive::{Changes, OnChange},
     runtime::RunningTasks,
+    watcher::Watched,
 };
 
 pub struct Unit;
 impl UserData for Unit {}
 
 pub struct Application<T = Unit> {
-    path: PathBuf,
+    loader: Box<dyn Loader>,
     user_data: Option<T>,
     timeout: Option<Duration>,
+    budget: Option<Duration>,
     reload: Option<Keybind>,
+    error_keybinds: Option<[Keybind; 3]>,
+    error_scroll_keybinds: Option<[Keybind; 2]>,
+    diff: bool,
     config: RunConfig,
     bindings: Bindings,
+    channels: Vec<Channel>,
+    sandbox: Option<Sandbox>,
+    on_change: Vec<(String, OnChange)>,
 }
 
 impl Application<Unit> {
     pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self::from_loader(FileLoader::new(path))
+    }
+
+    /// Runs a script from memory, e.g. one embedded with `include_str!`
+    ///
+    /// `name` is used in error messages
+    pub fn from_source(name: impl Into<String>, source: impl Into<String>) -> Self {
+        Self::from_loader(MemoryLoader::new(name, source))
+    }
+
+    pub fn from_loader(loader: impl Loader) -> Self {
         Self {
-            path: path.into(),
+            loader: Box::new(loader),
             user_data: None,
             timeout: None,
+            budget: None,
             reload: None,
+            error_keybinds: None,
+            error_scroll_keybinds: None,
+            diff: false,
             config: RunConfig::default(),
             bindings: Bindings::default_bindings(),
+            channels: Vec::new(),
+            sandbox: None,
+            on_change: Vec::new(),
         }
     }
 
@@ -47,11 +79,18 @@ impl Application<Unit> {
     {
         Application {
             user_data: Some(user_data),
-            path: self.path,
+            loader: self.loader,
             timeout: self.timeout,
+            budget: self.budget,
             reload: self.reload,
+            error_keybinds: self.error_keybinds,
+            error_scroll_keybinds: self.error_scroll_keybinds,
+            diff: self.diff,
             config: self.config,
             bindings: self.bindings,
+            channels: self.channels,
+            sandbox: self.sandbox,
+            on_change: self.on_change,
         }
     }
 }
@@ -60,16 +99,57 @@ impl<T> Application<T>
 where
     T: UserData + MaybeSend + 'static,
 {
+    /// Reloads the script when it, or a module it requires, changes
+    ///
+    /// Changes are coalesced until nothing has changed for `timeout`
     pub fn watch_timeout(mut self, timeout: Duration) -> Self {
         self.timeout = Some(timeout);
         self
     }
 
+    /// Limits how long the script can run on the ui thread at a time
+    ///
+    /// This applies to evaluating the script, handlers, lazies and timers
+    pub fn budget(mut self, budget: Duration) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
     pub fn reload_keybind(mut self, reload: impl Into<Keybind>) -> Self {
         self.reload = Some(reload.into());
         self
     }
 
+    /// Sets the keybinds to select the next or previous error, and to dismiss an error
+    ///
+    /// These default to `alt+n`, `alt+p` and `alt+d`
+    pub fn error_keybinds(
+        mut self,
+        next: impl Into<Keybind>,
+        prev: impl Into<Keybind>,
+        dismiss: impl Into<Keybind>,
+    ) -> Self {
+        self.error_keybinds = Some([next.into(), prev.into(), dismiss.into()]);
+        self
+    }
+
+    /// Sets the keybinds to scroll the selected error
+    ///
+    /// These default to `alt+k` and `alt+j`
+    pub fn error_scroll_keybinds(
+        mut self,
+        up: impl Into<Keybind>,
+        down: impl Into<Keybind>,
+    ) -> Self {
+        self.error_scroll_keybinds = Some([up.into(), down.into()]);
+        self
+    }
+
+    pub fn reload_diff(mut self, diff: bool) -> Self {
+        self.diff = diff;
+        self
+    }
+
     pub fn config(mut self, config: RunConfig) -> Self {
         self.config = config;
         self
@@ -80,6 +160,27 @@ where
         self
     }
 
+    pub fn channel(mut self, channel: Channel) -> Self {
+        self.channels.push(channel);
+        self
+    }
+
+    pub fn sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Calls `on_change` with the old and new value, after the frame in which
+    /// the persisted value `id` changed
+    pub fn on_value_change(
+        mut self,
+        id: impl Into<String>,
+        on_change: impl FnMut(&Value, &Value) + Send + 'static,
+    ) -> Self {
+        self.on_change.push((id.into(), Box::new(on_change)));
+        self
+    }
+
     pub async fn run(self) -> std::io::Result<()> {
         tokio::task::spawn_blocking(move || self.run_inner())
             .await
@@ -87,7 +188,7 @@ where
     }
 
     fn run_inner(self) -> std::io::Result<()> {
-        let lua = init_lua(&self.bindings).map_err(std::io::Error::other)?;
+        let lua = init_lua(&self.bindings, self.sandbox.as_ref()).map_err(std::io::Error::other)?;
         if let Some(user_data) = self.user_data {
             lua.globals()
                 .set("__USER_STATE", user_data)
@@ -95,19 +196,27 @@ where
         }
 
         lua.set_app_data(self.config.palette);
-
-        // TODO make this fail less hard
-        let mut script = match Script::new(self.path, self.timeout, &lua) {
-            Ok(script) => script,
-            Err(err) => {
-                eprintln!("cannot load script: {err}");
-                return Err(std::io::Error::other(err.to_string()));
-            }
-        };
+        lua.set_app_data(Channels::new(self.channels));
+        lua.set_app_data(Budget::new(self.budget));
+        for (id, on_change) in self.on_change {
+            Changes::on_host_change(&lua, id, on_change);
+        }
 
         let mapping = Mapping::from_bindings(self.bindings);
 
-        let mut errors = Errors::default();
+        let mut errors = match self.error_keybinds {
+            Some([next, prev, dismiss]) => Errors::new(next, prev, dismiss),
+            None => Errors::default(),
+        };
+        if let Some([up, down]) = self.error_scroll_keybinds {
+            errors = errors.scroll_keybinds(up, down);
+        }
+
+        // a broken script starts with an empty tree, it'll be loaded once it changes
+        let mut script = Script::unloaded(self.loader, self.timeout, &lua);
+        if let Err(err) = script.reload(&lua) {
+            errors.set_banner("cannot load", err);
+        }
         let mut notifications = Notifications::default();
 
         let mut term = Term::setup(
@@ -128,8 +237,9 @@ where
 
         let mut should_render = false;
         let mut last_resize = None;
+        let mut reported_size = None;
 
-        run_loop(fps, |_fr, dt| {
+        let result = run_loop(fps, |_fr, dt| {
             state.update(dt);
 
             lua.set_app_data(*state.palette());
@@ -145,6 +255,11 @@ where
                     break;
                 }
 
+                if errors.event(&ev) {
+                    should_render = true;
+                    continue;
+                }
+
                 if let Some(reload) = self.reload {
                     was_manually_reloaded ^= ev.is_keybind_pressed(reload);
                 }
@@ -159,25 +274,40 @@ where
                 should_render = true;
             }
 
+            for err in script.watch_errors() {
+                errors.handle_lua_error("watch", err);
+                should_render = true;
+            }
+
             if was_manually_reloaded || script.should_reload() {
                 Debug::clear();
                 profiling::scope!("reload script");
 
-                if let Err(err) = script.reload(&lua) {
-                    errors.handle_lua_error("cannot load", err);
-                    return Ok(true);
-                }
+                let previous = self
+                    .diff
+                    .then(|| TreeDump::build(&lua.app_data_ref::<Tree>().unwrap()));
 
                 // this can be blocking for a very long time
-                if let Err(err) = script.update(&lua) {
-                    errors.handle_lua_error("cannot evaluate", err);
-                    return Ok(true);
+                match script.reload(&lua) {
+                    Ok(()) => {
+                        // errors from the old script don't apply to the new one
+                        errors.clear();
+                        // the new script hasn't been told the size yet
+                        reported_size = None;
+                        let message = match previous {
+                            Some(previous) => {
+                                let tree = lua.app_data_ref::<Tree>().unwrap();
+                                let diff = TreeDiff::new(&previous, &TreeDump::build(&tree));
+                                format!("loaded new script: {}", diff.summary())
+                            }
+                            None => String::from("loaded new script"),
+                        };
+                        notifications.push(Notification::new(message, Duration::from_secs(3)));
+                    }
+                    // the previous tree is still in place, so keep rendering it
+                    Err(err) => errors.set_banner("cannot reload", err),
                 }
-
-                notifications.push(Notification::new(
-                    "loaded new script",
-                    Duration::from_secs(3),
-                ));
+                should_render = true;
             }
 
             if let Some(size) = last_resize {
@@ -187,53 +317,127 @@ where
                 should_render = true;
             }
 
-            lua.app_data_mut::<Tree>().unwrap().evaluate_lazies();
+            let size = surface.rect().size();
+            if reported_size != Some(size) {
+                reported_size = Some(size);
+                if let Err(err) = script.resize(&lua, size) {
+                    errors.handle_lua_error("on_resize", err);
+                }
+            }
+
+            if let Err(err) = script.frame(&lua, dt) {
+                errors.handle_lua_error("on_frame", err);
+            }
 
-            state.build(surface.rect(), |ui| {
-                let tree = lua.app_data_ref::<Tree>().unwrap();
-                let ctx = Context::new(
-                    &lua, //
-                    &tree,
-                    &tree.map[tree.root],
-                    tree.root,
-                );
-                profiling::scope!("evaluate tree");
-                mapping.evaluate(ui, ctx);
-            });
+            for err in RunningTasks::run_callbacks(&lua) {
+                errors.handle_lua_error("callback", err);
+            }
 
-            state.render(&mut CroppedSurface::new(surface.rect(), &mut surface));
+            for (_, err) in RunningTasks::take_failures(&lua) {
+                errors.handle_lua_error("task", err);
+            }
+
+            render_tree(&lua, &mapping, &mut state, &mut surface);
+            errors.collect_reported(&lua);
 
             notifications.render(0, &state.palette(), &mut surface);
-            errors.render(&mut surface);
+            errors.render(state.palette(), &mut surface);
 
             surface.render(&mut term.writer())?;
 
+            for err in Changes::notify(&lua) {
+                errors.handle_lua_error("on_change", err);
+            }
+
             Ok(true)
-        })
+        });
+
+        // give the script a chance to save its state before quitting
+        let exit = script.exit(&lua);
+        drop(term);
+        if let Err(err) = exit {
+            eprintln!("on_exit failed: {err}");
+        }
+        result
     }
 }
 
-pub(crate) fn init_lua(bindings: &Bindings) -> mlua::Result<mlua::Lua> {
-    let lua = mlua::Lua::new();
+pub(crate) fn init_lua(bindings: &Bindings, sandbox: Option<&Sandbox>) -> mlua::Result<mlua::Lua> {
+    let lua = match sandbox {
+        Some(sandbox) => sandbox.create_lua()?,
+        None => mlua::Lua::new(),
+    };
     lua.set_app_data(Tree::new(&lua)?);
     lua.set_app_data(RunningTasks::default());
+    lua.set_app_data(Budget::default());
+    lua.set_app_data(Reported::default());
+    lua.set_app_data(Changes::default());
 
     let globals = lua.globals();
     globals.set("lazy", lua.create_function(lazy)?)?;
+    globals.set("reactive", lua.create_function(Tree::add_reactive)?)?;
 
     hook_require(&lua)?;
 
-    for proxy in &bindings.proxies {
+    let proxies = bindings
+        .proxies
+        .iter()
+        .chain(bindings.bindings.iter().flat_map(|(spec, _)| spec.proxies))
+        .filter(|proxy| sandbox.is_none_or(|sandbox| sandbox.allows(proxy)));
+
+    for proxy in proxies {
         (proxy.register)(&globals, &lua)?;
     }
 
-    for (spec, _) in &bindings.bindings {
-        for proxy in spec.proxies {
-            (proxy.register)(&globals, &lua)?;
-        }
+    Ok(lua)
+}
+
+pub(crate) fn render_tree(
+    lua: &mlua::Lua,
+    mapping: &Mapping,
+    state: &mut State,
+    surface: &mut Surface,
+) {
+    let mut failed = Vec::new();
+    let recomputed = Budget::guard(lua, || {
+        failed = Changes::recompute(lua);
+        Ok(())
+    });
+    for err in failed.into_iter().chain(recomputed.err()) {
+        Reported::report(lua, "computed", err);
     }
 
-    Ok(lua)
+    let mut failed = Vec::new();
+    let rebuilt = Budget::guard(lua, || {
+        failed = Tree::rebuild_reactives(lua);
+        Ok(())
+    });
+    for err in failed.into_iter().chain(rebuilt.err()) {
+        Reported::report(lua, "reactive", err);
+    }
+
+    let lazies = Budget::guard(lua, || {
+        lua.app_data_mut::<Tree>().unwrap().evaluate_lazies();
+        Ok(())
+    });
+    if let Err(err) = lazies {
+        Reported::report(lua, "lazy", err);
+    }
+
+    state.build(surface.rect(), |ui| {
+        let tree = lua.app_data_ref::<Tree>().unwrap();
+        let ctx = Context::new(
+            lua, //
+            &tree,
+            &tree.map[tree.root],
+            tree.root,
+        );
+        profiling::scope!("evaluate tree");
+        mapping.evaluate(ui, ctx);
+    });
+
+    let rect = surface.rect();
+    state.render(&mut CroppedSurface::new(rect, surface));
 }
 
 fn lazy(lua: &mlua::Lua, table: mlua::Table) -> mlua::Result<()> {
@@ -247,18 +451,48 @@ fn lazy(lua: &mlua::Lua, table: mlua::Table) -> mlua::Result<()> {
 fn hook_require(lua: &mlua::Lua) -> mlua::Result<()> {
     let globals = lua.globals();
 
-    let require = globals.get::<mlua::Function>("require")?;
+    // a sandbox may not have `require`
+    let Ok(require) = globals.get::<mlua::Function>("require") else {
+        return Ok(());
+    };
     let loaded = lua.create_table()?;
     globals.set("__TOO_LOADED", loaded)?;
     let require = lua.create_function(move |lua, name: mlua::String| {
+        if is_loaded(lua, &name) {
+            return require.call::<mlua::Value>(name);
+        }
+
+        // this is resolved before loading so a broken module is still watched
+        let path = resolve_module(lua, &name);
+        if let (Some(path), Some(watched)) = (&path, lua.app_data_ref::<Watched>()) {
+            watched.insert(path);
+        }
+
         lua.globals()
             .get::<mlua::Table>("__TOO_LOADED")?
-            .set(&name, true)?;
+            .set(&name, path.unwrap_or_else(|| String::from("true")))?;
         require.call::<mlua::Value>(name)
     })?;
     globals.set("require", require)
 }
 
+fn is_loaded(lua: &mlua::Lua, name: &mlua::String) -> bool {
+    lua.globals()
+        .get::<mlua::Table>("package")
+        .and_then(|package| package.get::<mlua::Table>("loaded"))
+        .and_then(|loaded| loaded.get::<mlua::Value>(name))
+        .is_ok_and(|module| !module.is_nil())
+}
+
+// this returns `None` for modules that aren't files, e.g. ones that are preloaded,
+// and for modules that can't be resolved, e.g. if `package.path` was replaced by the script
+fn resolve_module(lua: &mlua::Lua, name: &mlua::String) -> Option<String> {
+    let package = lua.globals().get::<mlua::Table>("package").ok()?;
+    let search = package.get::<mlua::Function>("searchpath").ok()?;
+    let path = package.get::<mlua::String>("path").ok()?;
+    search.call::<Option<String>>((name, path)).ok().flatten()
+}
+
 fn run_loop<E>(target: f32, mut frame: impl FnMut(u64, f32) -> Result<bool, E>) -> Result<(), E> {
     const EMA_ALPHA: f32 = 0.1;
     let mut ema_avg = 1.0 / target;
@@ -128,6 +128,7 @@ impl RegisterProxy for mlua::Lua {
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Proxy {
+    pub name: &'static str,
     pub ty: fn() -> anno_lua::Type,
     pub register: fn(&mlua::Table, &mlua::Lua) -> mlua::Result<()>,
 }
@@ -138,6 +139,7 @@ impl Proxy {
         T: Register + Anno,
     {
         Self {
+            name: T::NAME,
             ty: T::lua_type,
             register: T::set_proxy,
         }
@@ -156,7 +156,7 @@ impl View for Button {
         let handler = params.handler.clone();
         let view = too::views::button(&params.text).class(params.apply_styling());
         if ui.show(view).clicked() {
-            let _ = handler.call::<()>(());
+            ctx.call::<()>(&handler, ());
         }
     }
 }
@@ -5,10 +5,23 @@ use too::view::{Palette, Style, StyleOptions, Ui, ViewExt as _};
 use crate::{
     Context, Mapping, TranslateClass,
     binding::{Spec, View},
-    bindings::Color,
+    bindings::{Color, Value},
     helper::expect_table,
 };
 
+/// The text of a label, either a string or a [`Value`] that is formatted
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabelText(pub String);
+
+impl FromLua for LabelText {
+    fn from_lua(value: mlua::Value, lua: &mlua::Lua) -> mlua::Result<Self> {
+        match value {
+            mlua::Value::UserData(ud) => Ok(Self(ud.borrow::<Value>()?.to_string())),
+            value => String::from_lua(value, lua).map(Self),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Anno)]
 #[anno(name = "Label", self)]
 pub enum LabelClass {
@@ -109,7 +122,8 @@ pub struct LabelParams {
     pub class: Option<LabelClass>,
 
     /// The text of the label
-    pub text: String,
+    #[anno(lua_type = "string|Value")]
+    pub text: LabelText,
 }
 
 impl FromLua for LabelParams {
@@ -136,7 +150,7 @@ impl View for Label {
             /// Label displays some text
             Self {
                 name: "label",
-                params: "string" | "LabelParams"
+                params: "string" | "Value" | "LabelParams"
             }
         }
     }
@@ -150,19 +164,19 @@ impl View for Label {
         type Apply = fn(Label) -> Label;
         type Class = fn(&Palette, StyleOptions) -> LabelStyle;
 
-        let Some(params) = ctx.params::<Either<String, LabelParams>>() else {
+        let Some(params) = ctx.params::<Either<LabelText, LabelParams>>() else {
             return Mapping::report_missing_data(ui, ctx.id, "label", "params");
         };
 
         let params = match params {
-            Either::Left(left) => {
+            Either::Left(LabelText(left)) => {
                 ui.show(label(left));
                 return;
             }
             Either::Right(params) => params,
         };
 
-        let mut label = label(params.text);
+        let mut label = label(params.text.0);
 
         let mut fg = None;
         if let Some(style) = params.style {
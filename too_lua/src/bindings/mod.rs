@@ -142,7 +142,7 @@ mod horizontal;
 pub use horizontal::Horizontal;
 
 mod label;
-pub use label::{Label, LabelClass, LabelParams, LabelStyle};
+pub use label::{Label, LabelClass, LabelParams, LabelStyle, LabelText};
 
 mod margin;
 pub use margin::{Margin, MarginParams};
@@ -193,4 +193,4 @@ mod color;
 pub use color::Color;
 
 mod value;
-pub use value::Value;
+pub use value::{Value, ValueMut};
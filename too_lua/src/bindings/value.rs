@@ -1,7 +1,9 @@
+use std::collections::BTreeMap;
+
 use anno_lua::Anno;
-use mlua::{AnyUserData, IntoLua as _};
+use mlua::{AnyUserData, FromLua as _, IntoLua as _, MetaMethod, UserDataRefMut};
 
-use crate::binding::Register;
+use crate::{binding::Register, reactive::Changes};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value {
@@ -10,6 +12,8 @@ pub enum Value {
     Signed(isize),
     Unsigned(usize),
     String(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
 }
 
 impl Value {
@@ -82,10 +86,410 @@ impl Value {
             _ => None,
         }
     }
+
+    pub fn list_ref(&self) -> Option<&Vec<Value>> {
+        match self {
+            Self::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn list_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Self::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn map_ref(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Self::Map(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn map_mut(&mut self) -> Option<&mut BTreeMap<String, Value>> {
+        match self {
+            Self::Map(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl Value {
+    pub(crate) const GLOBAL_KEY: &'static str = "__TOO_VALUES";
+
+    // tables can refer to themselves, so this stops at some point
+    const MAX_DEPTH: usize = 32;
+
+    pub(crate) fn from_lua_value(value: mlua::Value) -> mlua::Result<Self> {
+        Self::from_lua_value_at(value, 0)
+    }
+
+    fn from_lua_value_at(value: mlua::Value, depth: usize) -> mlua::Result<Self> {
+        Ok(match value {
+            mlua::Value::Boolean(value) => Self::Bool(value),
+            mlua::Value::Integer(value) => Self::Signed(value as isize),
+            mlua::Value::Number(value) => Self::Float(value as f32),
+            mlua::Value::String(value) => Self::String(value.to_string_lossy()),
+            mlua::Value::UserData(value) => Self::clone(&*value.borrow::<Self>()?),
+            mlua::Value::Table(..) if depth >= Self::MAX_DEPTH => {
+                return Err(mlua::Error::runtime("value is nested too deeply"));
+            }
+            // a table with only a sequence, or an empty table, is a list
+            mlua::Value::Table(table)
+                if table.raw_len() == table.pairs::<mlua::Value, mlua::Value>().count() =>
+            {
+                let list = table
+                    .sequence_values::<mlua::Value>()
+                    .map(|value| Self::from_lua_value_at(value?, depth + 1))
+                    .collect::<mlua::Result<_>>()?;
+                Self::List(list)
+            }
+            mlua::Value::Table(table) => {
+                let map = table
+                    .pairs::<mlua::String, mlua::Value>()
+                    .map(|pair| {
+                        let (key, value) = pair?;
+                        let value = Self::from_lua_value_at(value, depth + 1)?;
+                        Ok((key.to_string_lossy(), value))
+                    })
+                    .collect::<mlua::Result<_>>()?;
+                Self::Map(map)
+            }
+            _ => return Err(mlua::Error::runtime("invalid type")),
+        })
+    }
+
+    pub(crate) fn to_lua_value(&self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
+        match self {
+            Self::Bool(value) => value.into_lua(lua),
+            Self::Float(value) => value.into_lua(lua),
+            Self::Signed(value) => value.into_lua(lua),
+            Self::Unsigned(value) => value.into_lua(lua),
+            Self::String(value) => value.as_str().into_lua(lua),
+            Self::List(list) => lua
+                .create_sequence_from(
+                    list.iter()
+                        .map(|value| value.to_lua_value(lua))
+                        .collect::<mlua::Result<Vec<_>>>()?,
+                )
+                .map(mlua::Value::Table),
+            Self::Map(map) => lua
+                .create_table_from(
+                    map.iter()
+                        .map(|(key, value)| Ok((key.as_str(), value.to_lua_value(lua)?)))
+                        .collect::<mlua::Result<Vec<_>>>()?,
+                )
+                .map(mlua::Value::Table),
+        }
+    }
+
+    const fn kind(&self) -> &'static str {
+        match self {
+            Self::Bool(..) => "bool",
+            Self::Float(..) => "float",
+            Self::Signed(..) => "signed",
+            Self::Unsigned(..) => "unsigned",
+            Self::String(..) => "string",
+            Self::List(..) => "list",
+            Self::Map(..) => "map",
+        }
+    }
+
+    // lua lists start at 1
+    fn list_index(index: mlua::Integer) -> Option<usize> {
+        usize::try_from(index).ok()?.checked_sub(1)
+    }
+
+    fn get(&self, key: &mlua::Value) -> mlua::Result<Option<&Self>> {
+        match (self, key) {
+            (Self::List(list), mlua::Value::Integer(index)) => {
+                Ok(Self::list_index(*index).and_then(|index| list.get(index)))
+            }
+            (Self::Map(map), mlua::Value::String(key)) => Ok(map.get(&*key.to_str()?)),
+            (this, key) => Err(Self::cannot_index(this, key)),
+        }
+    }
+
+    fn set(&mut self, key: mlua::Value, value: mlua::Value) -> mlua::Result<()> {
+        match (self, key) {
+            (Self::List(list), mlua::Value::Integer(index)) => {
+                let index = Self::list_index(index)
+                    .filter(|&index| index <= list.len())
+                    .ok_or_else(|| mlua::Error::runtime(format!("index out of bounds: {index}")))?;
+
+                match value {
+                    mlua::Value::Nil if index < list.len() => _ = list.remove(index),
+                    mlua::Value::Nil => {}
+                    value if index == list.len() => list.push(Self::from_lua_value(value)?),
+                    value => list[index] = Self::from_lua_value(value)?,
+                }
+            }
+            (Self::Map(map), mlua::Value::String(key)) => {
+                let key = key.to_string_lossy();
+                match value {
+                    mlua::Value::Nil => _ = map.remove(&key),
+                    value => _ = map.insert(key, Self::from_lua_value(value)?),
+                }
+            }
+            (this, key) => return Err(Self::cannot_index(this, &key)),
+        }
+        Ok(())
+    }
+
+    fn cannot_index(&self, key: &mlua::Value) -> mlua::Error {
+        mlua::Error::runtime(format!(
+            "cannot index a {} value with: {}",
+            self.kind(),
+            key.type_name()
+        ))
+    }
+
+    // either side of an operator can be a value, which is read like `value.value` would be
+    fn operand(lua: &mlua::Lua, value: mlua::Value) -> mlua::Result<mlua::Value> {
+        match value {
+            mlua::Value::UserData(data) if data.is::<Self>() => {
+                Changes::read(lua, &data);
+                data.borrow::<Self>()?.to_lua_value(lua)
+            }
+            value => Ok(value),
+        }
+    }
+
+    fn number(lua: &mlua::Lua, value: mlua::Value) -> mlua::Result<Number> {
+        let value = Self::operand(lua, value)?;
+        Number::new(&value).ok_or_else(|| {
+            mlua::Error::runtime(format!(
+                "cannot do arithmetic on a {} value",
+                value.type_name()
+            ))
+        })
+    }
+
+    // integers stay integers, unless there is no integer operation (e.g. division)
+    //
+    // integers saturate rather than wrap, like `inc` does
+    fn arithmetic(
+        lua: &mlua::Lua,
+        (lhs, rhs): (mlua::Value, mlua::Value),
+        integer: Option<fn(mlua::Integer, mlua::Integer) -> mlua::Integer>,
+        float: fn(mlua::Number, mlua::Number) -> mlua::Number,
+    ) -> mlua::Result<mlua::Value> {
+        let (lhs, rhs) = (Self::number(lua, lhs)?, Self::number(lua, rhs)?);
+        Ok(match (lhs, rhs, integer) {
+            (Number::Integer(lhs), Number::Integer(rhs), Some(integer)) => {
+                mlua::Value::Integer(integer(lhs, rhs))
+            }
+            (lhs, rhs, _) => mlua::Value::Number(float(lhs.float(), rhs.float())),
+        })
+    }
+
+    fn compare(
+        lua: &mlua::Lua,
+        (lhs, rhs): (mlua::Value, mlua::Value),
+    ) -> mlua::Result<Option<std::cmp::Ordering>> {
+        let (lhs, rhs) = (Self::operand(lua, lhs)?, Self::operand(lua, rhs)?);
+        if let (mlua::Value::String(lhs), mlua::Value::String(rhs)) = (&lhs, &rhs) {
+            return Ok(Some(<[u8]>::cmp(&lhs.as_bytes(), &rhs.as_bytes())));
+        }
+        match (Number::new(&lhs), Number::new(&rhs)) {
+            (Some(lhs), Some(rhs)) => Ok(lhs.compare(rhs)),
+            _ => Err(mlua::Error::runtime(format!(
+                "cannot compare a {} value with a {} value",
+                lhs.type_name(),
+                rhs.type_name()
+            ))),
+        }
+    }
+
+    fn concat_operand(lua: &mlua::Lua, value: mlua::Value) -> mlua::Result<String> {
+        if let mlua::Value::UserData(data) = &value
+            && data.is::<Self>()
+        {
+            Changes::read(lua, data);
+            return Ok(data.borrow::<Self>()?.to_string());
+        }
+        let ty = value.type_name();
+        match lua.coerce_string(value)? {
+            Some(value) => Ok(value.to_string_lossy()),
+            None => Err(mlua::Error::runtime(format!(
+                "cannot concatenate a {ty} value"
+            ))),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Number {
+    Integer(mlua::Integer),
+    Float(mlua::Number),
+}
+
+impl Number {
+    const fn new(value: &mlua::Value) -> Option<Self> {
+        match value {
+            mlua::Value::Integer(value) => Some(Self::Integer(*value)),
+            mlua::Value::Number(value) => Some(Self::Float(*value)),
+            _ => None,
+        }
+    }
+
+    const fn float(self) -> mlua::Number {
+        match self {
+            Self::Integer(value) => value as mlua::Number,
+            Self::Float(value) => value,
+        }
+    }
+
+    fn compare(self, other: Self) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (Self::Integer(lhs), Self::Integer(rhs)) => Some(lhs.cmp(&rhs)),
+            (lhs, rhs) => lhs.float().partial_cmp(&rhs.float()),
+        }
+    }
+}
+
+// `clamp` panics if the bounds are out of order, or if either is NaN
+fn check_bounds<T: PartialOrd>(min: &T, max: &T) -> mlua::Result<()> {
+    if !min.partial_cmp(max).is_some_and(std::cmp::Ordering::is_le) {
+        return Err(mlua::Error::runtime("min must not be greater than max"));
+    }
+    Ok(())
 }
 
 impl Value {
-    const GLOBAL_KEY: &'static str = "__TOO_VALUES";
+    /// Updates a value from rust, rebuilding the reactive nodes that read it
+    pub fn update(
+        lua: &mlua::Lua,
+        data: &AnyUserData,
+        update: impl FnOnce(&mut Self),
+    ) -> mlua::Result<()> {
+        let mut value = ValueMut::new(lua, data)?;
+        update(&mut value);
+        Ok(())
+    }
+}
+
+/// A mutable borrow of a [`Value`] that records whether it was changed once it is dropped
+pub struct ValueMut<'a> {
+    lua: &'a mlua::Lua,
+    data: AnyUserData,
+    value: UserDataRefMut<Value>,
+    before: Value,
+}
+
+impl<'a> ValueMut<'a> {
+    pub(crate) fn new(lua: &'a mlua::Lua, data: &AnyUserData) -> mlua::Result<Self> {
+        let value = data.borrow_mut::<Value>()?;
+        Ok(Self {
+            lua,
+            data: data.clone(),
+            before: value.clone(),
+            value,
+        })
+    }
+}
+
+impl std::ops::Deref for ValueMut<'_> {
+    type Target = Value;
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl std::ops::DerefMut for ValueMut<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl Drop for ValueMut<'_> {
+    fn drop(&mut self) {
+        if *self.value != self.before {
+            Changes::write(self.lua, &self.data, &self.before);
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(value) => value.fmt(f),
+            Self::Float(value) => value.fmt(f),
+            Self::Signed(value) => value.fmt(f),
+            Self::Unsigned(value) => value.fmt(f),
+            Self::String(value) => value.fmt(f),
+            Self::List(list) => {
+                f.write_str("[")?;
+                for (i, value) in list.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    value.fmt(f)?;
+                }
+                f.write_str("]")
+            }
+            Self::Map(map) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Self::List(value)
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(value: BTreeMap<String, Value>) -> Self {
+        Self::Map(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<isize> for Value {
+    fn from(value: isize) -> Self {
+        Self::Signed(value)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Self::Unsigned(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
 }
 
 impl mlua::UserData for Value {
@@ -93,22 +497,14 @@ impl mlua::UserData for Value {
     where
         F: mlua::UserDataFields<Self>,
     {
-        fields.add_field_method_get("value", |lua, this| match this {
-            Self::Bool(value) => value.into_lua(lua),
-            Self::Float(value) => value.into_lua(lua),
-            Self::Signed(value) => value.into_lua(lua),
-            Self::Unsigned(value) => value.into_lua(lua),
-            Self::String(value) => value.as_str().into_lua(lua),
+        fields.add_field_function_get("value", |lua, data| {
+            Changes::read(lua, &data);
+            data.borrow::<Self>()?.to_lua_value(lua)
         });
 
-        fields.add_field_method_set("value", |_lua, this, value: mlua::Value| {
-            *this = match value {
-                mlua::Value::Boolean(value) => Self::Bool(value),
-                mlua::Value::Integer(value) => Self::Signed(value as isize),
-                mlua::Value::Number(value) => Self::Float(value as f32),
-                mlua::Value::String(value) => Self::String(value.to_string_lossy()),
-                _ => return Err(mlua::Error::runtime("invalid type")),
-            };
+        fields.add_field_function_set("value", |lua, data, value: mlua::Value| {
+            let value = Self::from_lua_value(value)?;
+            *ValueMut::new(lua, &data)? = value;
             Ok(())
         });
     }
@@ -118,14 +514,267 @@ impl mlua::UserData for Value {
         M: mlua::UserDataMethods<Self>,
     {
         methods.add_function("new", |lua, value: mlua::Value| {
-            let this = match value {
-                mlua::Value::Boolean(value) => Self::Bool(value),
-                mlua::Value::Integer(value) => Self::Signed(value as isize),
-                mlua::Value::Number(value) => Self::Float(value as f32),
-                mlua::Value::String(value) => Self::String(value.to_string_lossy()),
-                _ => return Err(mlua::Error::runtime("invalid type")),
+            Self::from_lua_value(value)?.into_lua(lua)
+        });
+
+        methods.add_function("list", |lua, items: Option<Vec<mlua::Value>>| {
+            let list = items
+                .into_iter()
+                .flatten()
+                .map(Self::from_lua_value)
+                .collect::<mlua::Result<_>>()?;
+            Self::List(list).into_lua(lua)
+        });
+
+        methods.add_function("map", |lua, items: Option<mlua::Table>| {
+            let map = match items {
+                Some(items) => match Self::from_lua_value(mlua::Value::Table(items))? {
+                    Self::Map(map) => map,
+                    // an empty table is treated as a list
+                    Self::List(list) if list.is_empty() => BTreeMap::new(),
+                    _ => return Err(mlua::Error::runtime("expected a table with string keys")),
+                },
+                None => BTreeMap::new(),
             };
-            this.into_lua(lua)
+            Self::Map(map).into_lua(lua)
+        });
+
+        methods.add_function("get", |lua, (data, key): (AnyUserData, mlua::Value)| {
+            Changes::read(lua, &data);
+            match data.borrow::<Self>()?.get(&key)? {
+                Some(value) => value.to_lua_value(lua),
+                None => Ok(mlua::Value::Nil),
+            }
+        });
+
+        methods.add_function(
+            "insert",
+            |lua, (data, first, second): (AnyUserData, mlua::Value, Option<mlua::Value>)| {
+                let mut this = ValueMut::new(lua, &data)?;
+                match (&mut *this, second) {
+                    (Self::List(list), None) => list.push(Self::from_lua_value(first)?),
+                    (Self::List(list), Some(value)) => {
+                        let index = mlua::Integer::from_lua(first, lua)?;
+                        let index = Self::list_index(index)
+                            .filter(|&index| index <= list.len())
+                            .ok_or_else(|| {
+                                mlua::Error::runtime(format!("index out of bounds: {index}"))
+                            })?;
+                        list.insert(index, Self::from_lua_value(value)?);
+                    }
+                    (Self::Map(map), Some(value)) => {
+                        let key = mlua::String::from_lua(first, lua)?;
+                        map.insert(key.to_string_lossy(), Self::from_lua_value(value)?);
+                    }
+                    (Self::Map(..), None) => {
+                        return Err(mlua::Error::runtime(
+                            "inserting into a map needs a key and a value: `map:insert(key, value)`",
+                        ));
+                    }
+                    (this, _) => {
+                        return Err(mlua::Error::runtime(format!(
+                            "cannot insert into a {} value",
+                            this.kind()
+                        )));
+                    }
+                }
+                Ok(())
+            },
+        );
+
+        methods.add_function("remove", |lua, (data, key): (AnyUserData, mlua::Value)| {
+            let mut this = ValueMut::new(lua, &data)?;
+            let removed = match (&mut *this, &key) {
+                (Self::List(list), mlua::Value::Integer(index)) => Self::list_index(*index)
+                    .filter(|&index| index < list.len())
+                    .map(|index| list.remove(index)),
+                (Self::Map(map), mlua::Value::String(key)) => map.remove(&*key.to_str()?),
+                (this, key) => return Err(this.cannot_index(key)),
+            };
+            match removed {
+                Some(value) => value.to_lua_value(lua),
+                None => Ok(mlua::Value::Nil),
+            }
+        });
+
+        methods.add_meta_function(
+            MetaMethod::Index,
+            |lua, (data, key): (AnyUserData, mlua::Value)| {
+                Changes::read(lua, &data);
+                match data.borrow::<Self>()?.get(&key)? {
+                    Some(value) => value.to_lua_value(lua),
+                    None => Ok(mlua::Value::Nil),
+                }
+            },
+        );
+
+        methods.add_meta_function(
+            MetaMethod::NewIndex,
+            |lua, (data, key, value): (AnyUserData, mlua::Value, mlua::Value)| {
+                ValueMut::new(lua, &data)?.set(key, value)
+            },
+        );
+
+        methods.add_meta_function(MetaMethod::Len, |lua, data: AnyUserData| {
+            Changes::read(lua, &data);
+            match &*data.borrow::<Self>()? {
+                Self::List(list) => Ok(list.len()),
+                Self::Map(map) => Ok(map.len()),
+                Self::String(string) => Ok(string.len()),
+                this => Err(mlua::Error::runtime(format!(
+                    "cannot get the length of a {} value",
+                    this.kind()
+                ))),
+            }
+        });
+
+        // this iterates over a copy, so the value can be changed while iterating
+        methods.add_meta_function(MetaMethod::Pairs, |lua, data: AnyUserData| {
+            Changes::read(lua, &data);
+            let this = data.borrow::<Self>()?;
+            if !matches!(&*this, Self::List(..) | Self::Map(..)) {
+                return Err(mlua::Error::runtime(format!(
+                    "cannot iterate over a {} value",
+                    this.kind()
+                )));
+            }
+            let next = lua.globals().get::<mlua::Function>("next")?;
+            Ok((next, this.to_lua_value(lua)?, mlua::Value::Nil))
+        });
+
+        methods.add_function("toggle", |lua, data: AnyUserData| {
+            let mut this = ValueMut::new(lua, &data)?;
+            let Self::Bool(value) = &mut *this else {
+                return Err(mlua::Error::runtime(format!(
+                    "cannot toggle a {} value",
+                    this.kind()
+                )));
+            };
+            *value = !*value;
+            Ok(*value)
+        });
+
+        methods.add_function(
+            "inc",
+            |lua, (data, step): (AnyUserData, Option<mlua::Value>)| {
+                let step = step.unwrap_or(mlua::Value::Integer(1));
+                let mut this = ValueMut::new(lua, &data)?;
+                match &mut *this {
+                    Self::Float(value) => *value += f32::from_lua(step, lua)?,
+                    Self::Signed(value) => {
+                        *value = value.saturating_add(isize::from_lua(step, lua)?);
+                    }
+                    Self::Unsigned(value) => {
+                        *value = value.saturating_add_signed(isize::from_lua(step, lua)?);
+                    }
+                    this => {
+                        return Err(mlua::Error::runtime(format!(
+                            "cannot increment a {} value",
+                            this.kind()
+                        )));
+                    }
+                }
+                this.to_lua_value(lua)
+            },
+        );
+
+        methods.add_function(
+            "clamp",
+            |lua, (data, min, max): (AnyUserData, mlua::Value, mlua::Value)| {
+                let mut this = ValueMut::new(lua, &data)?;
+                match &mut *this {
+                    Self::Float(value) => {
+                        let (min, max) = (f32::from_lua(min, lua)?, f32::from_lua(max, lua)?);
+                        check_bounds(&min, &max)?;
+                        *value = value.clamp(min, max);
+                    }
+                    Self::Signed(value) => {
+                        let (min, max) = (isize::from_lua(min, lua)?, isize::from_lua(max, lua)?);
+                        check_bounds(&min, &max)?;
+                        *value = value.clamp(min, max);
+                    }
+                    Self::Unsigned(value) => {
+                        let (min, max) = (usize::from_lua(min, lua)?, usize::from_lua(max, lua)?);
+                        check_bounds(&min, &max)?;
+                        *value = value.clamp(min, max);
+                    }
+                    this => {
+                        return Err(mlua::Error::runtime(format!(
+                            "cannot clamp a {} value",
+                            this.kind()
+                        )));
+                    }
+                }
+                this.to_lua_value(lua)
+            },
+        );
+
+        methods.add_meta_function(MetaMethod::Add, |lua, args: (mlua::Value, mlua::Value)| {
+            Self::arithmetic(lua, args, Some(mlua::Integer::saturating_add), |l, r| l + r)
+        });
+
+        methods.add_meta_function(MetaMethod::Sub, |lua, args: (mlua::Value, mlua::Value)| {
+            Self::arithmetic(lua, args, Some(mlua::Integer::saturating_sub), |l, r| l - r)
+        });
+
+        methods.add_meta_function(MetaMethod::Mul, |lua, args: (mlua::Value, mlua::Value)| {
+            Self::arithmetic(lua, args, Some(mlua::Integer::saturating_mul), |l, r| l * r)
+        });
+
+        // like lua, division always results in a float
+        methods.add_meta_function(MetaMethod::Div, |lua, args: (mlua::Value, mlua::Value)| {
+            Self::arithmetic(lua, args, None, |l, r| l / r)
+        });
+
+        methods.add_meta_function(MetaMethod::Unm, |lua, data: AnyUserData| {
+            Ok(match Self::number(lua, mlua::Value::UserData(data))? {
+                Number::Integer(value) => mlua::Value::Integer(value.saturating_neg()),
+                Number::Float(value) => mlua::Value::Number(-value),
+            })
+        });
+
+        // lua only calls this when both sides are userdata
+        methods.add_meta_function(
+            MetaMethod::Eq,
+            |lua, (lhs, rhs): (AnyUserData, AnyUserData)| {
+                Changes::read(lua, &lhs);
+                Changes::read(lua, &rhs);
+                let (Ok(lhs), Ok(rhs)) = (lhs.borrow::<Self>(), rhs.borrow::<Self>()) else {
+                    return Ok(false);
+                };
+                Ok(*lhs == *rhs)
+            },
+        );
+
+        methods.add_meta_function(MetaMethod::Lt, |lua, args: (mlua::Value, mlua::Value)| {
+            Ok(Self::compare(lua, args)? == Some(std::cmp::Ordering::Less))
+        });
+
+        methods.add_meta_function(MetaMethod::Le, |lua, args: (mlua::Value, mlua::Value)| {
+            Ok(Self::compare(lua, args)?.is_some_and(std::cmp::Ordering::is_le))
+        });
+
+        methods.add_meta_function(MetaMethod::ToString, |lua, data: AnyUserData| {
+            Changes::read(lua, &data);
+            Ok(data.borrow::<Self>()?.to_string())
+        });
+
+        methods.add_meta_function(
+            MetaMethod::Concat,
+            |lua, (lhs, rhs): (mlua::Value, mlua::Value)| {
+                let mut out = Self::concat_operand(lua, lhs)?;
+                out.push_str(&Self::concat_operand(lua, rhs)?);
+                Ok(out)
+            },
+        );
+
+        methods.add_function(
+            "on_change",
+            |lua, (data, func): (AnyUserData, mlua::Function)| Changes::on_change(lua, &data, func),
+        );
+
+        methods.add_function("computed", |lua, compute: mlua::Function| {
+            Changes::computed(lua, compute)
         });
 
         methods.add_function(
@@ -143,14 +792,7 @@ impl mlua::UserData for Value {
                 match table.get::<AnyUserData>(&id) {
                     Ok(value) => value.into_lua(lua),
                     Err(..) => {
-                        let this = match value {
-                            mlua::Value::Boolean(value) => Self::Bool(value),
-                            mlua::Value::Integer(value) => Self::Signed(value as isize),
-                            mlua::Value::Number(value) => Self::Float(value as f32),
-                            mlua::Value::String(value) => Self::String(value.to_string_lossy()),
-                            _ => return Err(mlua::Error::runtime("invalid type")),
-                        };
-                        let this = this.into_lua(lua)?;
+                        let this = Self::from_lua_value(value)?.into_lua(lua)?;
                         table.set(id, this.clone())?;
                         Ok(this)
                     }
@@ -174,18 +816,44 @@ impl Register for Value {
 impl Anno for Value {
     fn lua_type() -> anno_lua::Type {
         anno_lua::Type::Class(anno_lua::Class {
-            docs: &["A shared value between lua and rust"],
+            docs: &[
+                "A shared value between lua and rust",
+                "",
+                "lists and maps are indexed like tables. unlike a table, setting a list item",
+                "to nil removes it and shifts the items after it down, so a list has no holes",
+            ],
             name: "Value",
             exact: true,
             fields: &[
                 anno_lua::Field {
                     name: "new",
-                    ty: "fun(value: integer|number|boolean|string): Value",
+                    ty: "fun(value: integer|number|boolean|string|table): Value",
                     docs: &["create a new value"],
                 },
+                anno_lua::Field {
+                    name: "list",
+                    ty: "fun(items: any[]?): Value",
+                    docs: &["create a new list value"],
+                },
+                anno_lua::Field {
+                    name: "map",
+                    ty: "fun(items: table<string, any>?): Value",
+                    docs: &["create a new map value"],
+                },
+                anno_lua::Field {
+                    name: "computed",
+                    ty: "fun(compute: fun(): integer|number|boolean|string|table): Value",
+                    docs: &[
+                        "create a value computed by a function",
+                        "",
+                        "this is computed again when a value read by the function changes,",
+                        "before the next frame is drawn. reading it right after changing",
+                        "one of those values, in the same handler, gives the previous result",
+                    ],
+                },
                 anno_lua::Field {
                     name: "persist",
-                    ty: "fun(id: string, value: integer|number|boolean|string): Value",
+                    ty: "fun(id: string, value: integer|number|boolean|string|table): Value",
                     docs: &["create a new value, persisted and accessible via `id`"],
                 },
                 anno_lua::Field {
@@ -193,9 +861,62 @@ impl Anno for Value {
                     ty: "fun(id: string): boolean",
                     docs: &["destroys a persisted value `id`, if it exists"],
                 },
+                anno_lua::Field {
+                    name: "get",
+                    ty: "fun(self: Value, key: integer|string): any",
+                    docs: &[
+                        "get an item of a list, or an entry of a map",
+                        "",
+                        "this is the same as indexing it, but works for keys that are also methods",
+                    ],
+                },
+                anno_lua::Field {
+                    name: "insert",
+                    ty: "fun(self: Value, key: any, value: any?): nil",
+                    docs: &[
+                        "push an item to a list, insert an item into a list at an index,",
+                        "or insert an entry into a map",
+                    ],
+                },
+                anno_lua::Field {
+                    name: "remove",
+                    ty: "fun(self: Value, key: integer|string): any",
+                    docs: &["remove an item from a list or an entry from a map, returning it"],
+                },
+                anno_lua::Field {
+                    name: "toggle",
+                    ty: "fun(self: Value): boolean",
+                    docs: &["flips a boolean value, returning the new value"],
+                },
+                anno_lua::Field {
+                    name: "inc",
+                    ty: "fun(self: Value, step: number?): number",
+                    docs: &[
+                        "adds `step` (or 1) to a number value, returning the new value",
+                        "",
+                        "integer values saturate instead of overflowing, like the arithmetic operators",
+                    ],
+                },
+                anno_lua::Field {
+                    name: "clamp",
+                    ty: "fun(self: Value, min: number, max: number): number",
+                    docs: &[
+                        "clamps a number value between `min` and `max`, returning the new value",
+                    ],
+                },
+                anno_lua::Field {
+                    name: "on_change",
+                    ty: "fun(self: Value, callback: fun(old: integer|number|boolean|string|table, new: integer|number|boolean|string|table): nil): integer",
+                    docs: &[
+                        "calls a function after the frame in which the value changed",
+                        "",
+                        "this returns an id that you can use to stop the subscription,",
+                        "subscriptions are also stopped when the script is reloaded",
+                    ],
+                },
                 anno_lua::Field {
                     name: "value",
-                    ty: "integer|number|boolean|string",
+                    ty: "integer|number|boolean|string|table",
                     docs: &["get the inner value"],
                 },
             ],
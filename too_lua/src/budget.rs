@@ -0,0 +1,78 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use mlua::{HookTriggers, VmState};
+
+/// How long lua is allowed to run on the ui thread before it is aborted
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Budget {
+    limit: Option<Duration>,
+    active: bool,
+}
+
+impl Budget {
+    // how often the deadline is checked
+    const INSTRUCTIONS: u32 = 1000;
+
+    pub(crate) const fn new(limit: Option<Duration>) -> Self {
+        Self {
+            limit,
+            active: false,
+        }
+    }
+
+    /// Runs `f` with the budget enforced
+    ///
+    /// This returns an error if the budget was exceeded, even if `f` swallowed the error
+    pub(crate) fn guard<R>(
+        lua: &mlua::Lua,
+        f: impl FnOnce() -> mlua::Result<R>,
+    ) -> mlua::Result<R> {
+        // if the budget is already active then an outer call is enforcing it
+        let limit = lua.app_data_mut::<Self>().and_then(|mut budget| {
+            let limit = budget.limit.filter(|_| !budget.active)?;
+            budget.active = true;
+            Some(limit)
+        });
+
+        let Some(limit) = limit else { return f() };
+
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let deadline = Instant::now() + limit;
+
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(Self::INSTRUCTIONS),
+            {
+                let exceeded = Arc::clone(&exceeded);
+                move |_lua, _debug| {
+                    if Instant::now() < deadline {
+                        return Ok(VmState::Continue);
+                    }
+                    exceeded.store(true, Ordering::Relaxed);
+                    Err(Self::exceeded(limit))
+                }
+            },
+        );
+
+        let out = f();
+
+        lua.remove_hook();
+        if let Some(mut budget) = lua.app_data_mut::<Self>() {
+            budget.active = false;
+        }
+
+        if exceeded.load(Ordering::Relaxed) {
+            return Err(Self::exceeded(limit));
+        }
+        out
+    }
+
+    fn exceeded(limit: Duration) -> mlua::Error {
+        mlua::Error::runtime(format!("script exceeded its budget of {limit:?}"))
+    }
+}
@@ -1,8 +1,10 @@
 use anno_lua::Anno;
 
 use crate::{
-    Proxy,
+    Channel, Proxy,
     bindings::Color,
+    fs::Fs,
+    process::Process,
     runtime::{Duration, Runtime},
 };
 
@@ -60,6 +62,9 @@ register_enum! {
 pub const BUILTIN: &[Proxy] = &[
     Proxy::new::<Runtime>(), //
     Proxy::new::<Duration>(),
+    Proxy::new::<Channel>(),
+    Proxy::new::<Process>(),
+    Proxy::new::<Fs>(),
     Proxy::new::<PaletteKind>(),
     Proxy::new::<Color>(),
 ];
@@ -0,0 +1,189 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anno_lua::Anno;
+use mlua::{FromLua, UserData, UserDataRef};
+use tokio::sync::{
+    Mutex,
+    mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+};
+
+use crate::{Register, bindings::Value};
+
+#[derive(Clone)]
+struct Queue {
+    sender: UnboundedSender<Value>,
+    receiver: Arc<Mutex<UnboundedReceiver<Value>>>,
+}
+
+impl Queue {
+    fn new() -> Self {
+        let (sender, receiver) = unbounded_channel();
+        Self {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+        }
+    }
+
+    fn send(&self, value: Value) -> bool {
+        self.sender.send(value).is_ok()
+    }
+
+    async fn recv(&self) -> Option<Value> {
+        self.receiver.lock().await.recv().await
+    }
+
+    fn try_recv(&self) -> Option<Value> {
+        self.receiver.try_lock().ok()?.try_recv().ok()
+    }
+}
+
+/// A named, bidirectional channel between the host and lua
+///
+/// The host sends with [`Channel::send`] and lua receives with `channel:recv()`,
+/// lua sends with `channel:send(value)` and the host receives with [`Channel::recv`]
+#[derive(Clone)]
+pub struct Channel {
+    name: Arc<str>,
+    to_lua: Queue,
+    to_host: Queue,
+}
+
+impl Channel {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: Arc::from(name.into()),
+            to_lua: Queue::new(),
+            to_host: Queue::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn send(&self, value: impl Into<Value>) -> bool {
+        self.to_lua.send(value.into())
+    }
+
+    pub async fn recv(&self) -> Option<Value> {
+        self.to_host.recv().await
+    }
+
+    pub fn try_recv(&self) -> Option<Value> {
+        self.to_host.try_recv()
+    }
+}
+
+impl std::fmt::Debug for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Channel").field("name", &self.name).finish()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Channels {
+    map: HashMap<Arc<str>, Channel>,
+}
+
+impl Channels {
+    pub(crate) fn new(channels: impl IntoIterator<Item = Channel>) -> Self {
+        Self {
+            map: channels
+                .into_iter()
+                .map(|channel| (Arc::clone(&channel.name), channel))
+                .collect(),
+        }
+    }
+}
+
+impl FromLua for Channel {
+    fn from_lua(value: mlua::Value, _lua: &mlua::Lua) -> mlua::Result<Self> {
+        let mlua::Value::UserData(ud) = value else {
+            return Err(mlua::Error::runtime(format!(
+                "expected Channel, got: {}",
+                value.type_name(),
+            )));
+        };
+        ud.borrow::<Self>().map(|c| c.clone())
+    }
+}
+
+impl UserData for Channel {
+    fn add_methods<M>(methods: &mut M)
+    where
+        M: mlua::UserDataMethods<Self>,
+    {
+        methods.add_function("open", |lua, name: mlua::String| {
+            let name = name.to_str()?;
+            lua.app_data_ref::<Channels>()
+                .and_then(|channels| channels.map.get(&*name).cloned())
+                .ok_or_else(|| mlua::Error::runtime(format!("unknown channel: {name}")))
+        });
+
+        methods.add_async_method("recv", |lua, this: UserDataRef<Self>, ()| {
+            let queue = this.to_lua.clone();
+            async move {
+                match queue.recv().await {
+                    Some(value) => value.to_lua_value(&lua),
+                    None => Ok(mlua::Value::Nil),
+                }
+            }
+        });
+
+        methods.add_method("try_recv", |lua, this, ()| match this.to_lua.try_recv() {
+            Some(value) => value.to_lua_value(lua),
+            None => Ok(mlua::Value::Nil),
+        });
+
+        methods.add_method("send", |_lua, this, value: mlua::Value| {
+            let value = match value {
+                mlua::Value::UserData(ud) => ud.borrow::<Value>()?.clone(),
+                value => Value::from_lua_value(value)?,
+            };
+            Ok(this.to_host.send(value))
+        });
+
+        methods.add_method("name", |_lua, this, ()| Ok(this.name.to_string()));
+    }
+}
+
+impl Register for Channel {
+    const NAME: &'static str = "Channel";
+}
+
+impl Anno for Channel {
+    fn lua_type() -> anno_lua::Type {
+        anno_lua::Type::Class(anno_lua::Class {
+            exact: true,
+            docs: &["A named channel between lua and the host"],
+            name: "Channel",
+            fields: &[
+                anno_lua::Field {
+                    name: "open",
+                    ty: "fun(name: string): Channel",
+                    docs: &["opens a channel that the host created"],
+                },
+                anno_lua::Field {
+                    name: "recv",
+                    ty: "fun(self: Channel): integer|number|boolean|string|table|nil",
+                    docs: &["waits for a value sent by the host"],
+                },
+                anno_lua::Field {
+                    name: "try_recv",
+                    ty: "fun(self: Channel): integer|number|boolean|string|table|nil",
+                    docs: &["gets a value sent by the host, if one is available"],
+                },
+                anno_lua::Field {
+                    name: "send",
+                    ty: "fun(self: Channel, value: Value|integer|number|boolean|string|table): boolean",
+                    docs: &["sends a value to the host"],
+                },
+                anno_lua::Field {
+                    name: "name",
+                    ty: "fun(self: Channel): string",
+                    docs: &["gets the name of the channel"],
+                },
+            ],
+        })
+    }
+}
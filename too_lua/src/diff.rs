@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use crate::{NodeDump, Tree, TreeDump};
+
+/// A structural change between two trees
+///
+/// Nodes are identified by their path from the root, e.g. `vertical[0]/label[1]`,
+/// where the index counts siblings with the same name.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TreeChange {
+    Added {
+        path: String,
+    },
+    Removed {
+        path: String,
+    },
+    Moved {
+        from: String,
+        to: String,
+    },
+    Changed {
+        path: String,
+        old: serde_json::Value,
+        new: serde_json::Value,
+    },
+}
+
+impl std::fmt::Display for TreeChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added { path } => write!(f, "+ {path}"),
+            Self::Removed { path } => write!(f, "- {path}"),
+            Self::Moved { from, to } => write!(f, "> {from} -> {to}"),
+            Self::Changed { path, old, new } => write!(f, "~ {path}: {old} -> {new}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TreeDiff {
+    pub changes: Vec<TreeChange>,
+}
+
+impl TreeDiff {
+    pub fn between(old: &Tree, new: &Tree) -> Self {
+        Self::new(&TreeDump::build(old), &TreeDump::build(new))
+    }
+
+    pub fn new(old: &TreeDump, new: &TreeDump) -> Self {
+        let old_paths = paths(old);
+        let new_paths = paths(new);
+
+        let old_lookup = old_paths
+            .iter()
+            .map(|(path, node)| (path.as_str(), *node))
+            .collect::<HashMap<_, _>>();
+        let new_lookup = new_paths
+            .iter()
+            .map(|(path, node)| (path.as_str(), *node))
+            .collect::<HashMap<_, _>>();
+
+        // only report the top-most node of an added or removed subtree
+        let is_top = |path: &str, other: &HashMap<&str, &NodeDump>| {
+            path.rsplit_once('/')
+                .is_none_or(|(parent, _)| other.contains_key(parent))
+        };
+
+        let mut added = new_paths
+            .iter()
+            .filter(|(path, _)| !old_lookup.contains_key(path.as_str()))
+            .filter(|(path, _)| is_top(path, &old_lookup))
+            .collect::<Vec<_>>();
+
+        // each signature covers a whole subtree, so they're only built once
+        let mut removed = old_paths
+            .iter()
+            .filter(|(path, _)| !new_lookup.contains_key(path.as_str()))
+            .filter(|(path, _)| is_top(path, &new_lookup))
+            .map(|(path, node)| (path, signature(old, node)))
+            .collect::<Vec<_>>();
+
+        let mut changes = vec![];
+
+        // a subtree that was removed in one place and added in another, unchanged, was moved
+        added.retain(|(to, node)| {
+            let wanted = signature(new, node);
+            let Some(pos) = removed.iter().position(|(_, removed)| *removed == wanted) else {
+                return true;
+            };
+
+            let (from, _) = removed.remove(pos);
+            changes.push(TreeChange::Moved {
+                from: from.clone(),
+                to: to.clone(),
+            });
+            false
+        });
+
+        changes.extend(
+            removed
+                .into_iter()
+                .map(|(path, _)| TreeChange::Removed { path: path.clone() }),
+        );
+
+        for (path, node) in &new_paths {
+            match old_lookup.get(path.as_str()) {
+                Some(before) if before.params != node.params => changes.push(TreeChange::Changed {
+                    path: path.clone(),
+                    old: before.params.clone(),
+                    new: node.params.clone(),
+                }),
+                None if added.iter().any(|(added, _)| added == path) => {
+                    changes.push(TreeChange::Added { path: path.clone() })
+                }
+                _ => {}
+            }
+        }
+
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        let (mut added, mut removed, mut moved, mut changed) = (0, 0, 0, 0);
+        for change in &self.changes {
+            match change {
+                TreeChange::Added { .. } => added += 1,
+                TreeChange::Removed { .. } => removed += 1,
+                TreeChange::Moved { .. } => moved += 1,
+                TreeChange::Changed { .. } => changed += 1,
+            }
+        }
+
+        let parts = [
+            (added, "added"),
+            (removed, "removed"),
+            (moved, "moved"),
+            (changed, "changed"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{count} {label}"))
+        .collect::<Vec<_>>();
+
+        if parts.is_empty() {
+            return String::from("no changes");
+        }
+        parts.join(", ")
+    }
+}
+
+impl std::fmt::Display for TreeDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+fn paths(dump: &TreeDump) -> Vec<(String, &NodeDump)> {
+    fn visit<'a>(
+        dump: &'a TreeDump,
+        node: &'a NodeDump,
+        prefix: Option<&str>,
+        out: &mut Vec<(String, &'a NodeDump)>,
+    ) {
+        let mut seen = HashMap::<&str, usize>::new();
+        for child in dump.children(node) {
+            let index = seen.entry(&child.name).or_default();
+            let path = match prefix {
+                Some(prefix) => format!("{prefix}/{name}[{index}]", name = child.name),
+                None => format!("{name}[{index}]", name = child.name),
+            };
+            *index += 1;
+
+            out.push((path.clone(), child));
+            visit(dump, child, Some(&path), out);
+        }
+    }
+
+    let mut out = vec![];
+    if let Some(root) = dump.root() {
+        visit(dump, root, None, &mut out);
+    }
+    out
+}
+
+fn signature(dump: &TreeDump, node: &NodeDump) -> serde_json::Value {
+    serde_json::json!({
+        "name": node.name,
+        "params": node.params,
+        "children": dump
+            .children(node)
+            .map(|child| signature(dump, child))
+            .collect::<Vec<_>>(),
+    })
+}
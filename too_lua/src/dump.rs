@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{LuaId, Tree, bindings::Value};
+
+/// A structured copy of a [`Tree`], with its params converted to plain json values
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TreeDump {
+    pub root: usize,
+    pub nodes: Vec<NodeDump>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeDump {
+    pub id: usize,
+    pub name: String,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub params: serde_json::Value,
+}
+
+impl TreeDump {
+    // this walks down from the root, so nodes detached from the tree aren't included
+    pub fn build(tree: &Tree) -> Self {
+        let mut ids = vec![tree.root];
+        let mut queue = vec![tree.root];
+        while let Some(id) = queue.pop() {
+            let children = &tree.map[id].children;
+            ids.extend_from_slice(children);
+            queue.extend_from_slice(children);
+        }
+        ids.sort_unstable();
+
+        let nodes = ids
+            .into_iter()
+            .map(|id| {
+                let node = &tree.map[id];
+                NodeDump {
+                    id: id.index(),
+                    name: tree
+                        .names
+                        .get(&id)
+                        .map(|name| name.to_string_lossy())
+                        .unwrap_or_default(),
+                    parent: node.parent.map(LuaId::index),
+                    children: node.children.iter().copied().map(LuaId::index).collect(),
+                    params: to_json(&node.data, 0),
+                }
+            })
+            .collect();
+
+        Self {
+            root: tree.root.index(),
+            nodes,
+        }
+    }
+
+    // nodes are sorted by id, but ids can have gaps
+    pub fn get(&self, id: usize) -> Option<&NodeDump> {
+        let index = self.nodes.binary_search_by_key(&id, |node| node.id).ok()?;
+        self.nodes.get(index)
+    }
+
+    pub fn root(&self) -> Option<&NodeDump> {
+        self.get(self.root)
+    }
+
+    pub fn children<'a>(&'a self, node: &'a NodeDump) -> impl Iterator<Item = &'a NodeDump> + 'a {
+        node.children.iter().filter_map(|&id| self.get(id))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("tree dump is valid json")
+    }
+
+    pub fn from_json(data: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(data)
+    }
+}
+
+// tables can be self-referential, so stop descending at some point
+const MAX_DEPTH: usize = 32;
+
+fn to_json(value: &mlua::Value, depth: usize) -> serde_json::Value {
+    use serde_json::Value as Json;
+
+    match value {
+        mlua::Value::Boolean(value) => Json::Bool(*value),
+        mlua::Value::Integer(value) => Json::from(*value),
+        mlua::Value::Number(value) => Json::from(*value),
+        mlua::Value::String(value) => Json::String(value.to_string_lossy()),
+
+        mlua::Value::Table(table) if depth < MAX_DEPTH => {
+            let len = table.raw_len();
+            let is_array = len > 0 && table.pairs::<mlua::Value, mlua::Value>().count() == len;
+            if is_array {
+                return table
+                    .sequence_values::<mlua::Value>()
+                    .flatten()
+                    .map(|value| to_json(&value, depth + 1))
+                    .collect();
+            }
+
+            table
+                .pairs::<mlua::Value, mlua::Value>()
+                .flatten()
+                .map(|(key, value)| (key_name(&key), to_json(&value, depth + 1)))
+                .collect::<serde_json::Map<_, _>>()
+                .into()
+        }
+
+        mlua::Value::UserData(ud) => match ud.borrow::<Value>() {
+            Ok(value) => value_to_json(&value),
+            Err(..) => Json::String(String::from("<userdata>")),
+        },
+
+        mlua::Value::Function(..) => Json::String(String::from("<function>")),
+        mlua::Value::Thread(..) => Json::String(String::from("<thread>")),
+        _ => Json::Null,
+    }
+}
+
+fn key_name(key: &mlua::Value) -> String {
+    match key {
+        mlua::Value::String(key) => key.to_string_lossy(),
+        mlua::Value::Integer(key) => key.to_string(),
+        mlua::Value::Number(key) => key.to_string(),
+        mlua::Value::Boolean(key) => key.to_string(),
+        key => format!("<{}>", key.type_name()),
+    }
+}
+
+fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Bool(value) => (*value).into(),
+        Value::Float(value) => (*value).into(),
+        Value::Signed(value) => (*value).into(),
+        Value::Unsigned(value) => (*value).into(),
+        Value::String(value) => value.as_str().into(),
+        Value::List(list) => list.iter().map(value_to_json).collect(),
+        Value::Map(map) => map
+            .iter()
+            .map(|(key, value)| (key.clone(), value_to_json(value)))
+            .collect(),
+    }
+}
@@ -1,73 +1,399 @@
-use too::{math::pos2, renderer::Grapheme};
+use std::path::Path;
 
+use too::{
+    backend::{Event, Keybind},
+    math::pos2,
+    renderer::{Grapheme, Pixel, Rgba, Surface},
+    view::Palette,
+};
+
+/// Errors raised while the ui is being built, e.g. from handlers
 #[derive(Default)]
+pub(crate) struct Reported {
+    errors: Vec<(&'static str, mlua::Error)>,
+}
+
+impl Reported {
+    pub(crate) fn report(lua: &mlua::Lua, hint: &'static str, err: mlua::Error) {
+        if let Some(mut reported) = lua.app_data_mut::<Self>() {
+            reported.errors.push((hint, err));
+        }
+    }
+
+    pub(crate) fn take(lua: &mlua::Lua) -> Vec<(&'static str, mlua::Error)> {
+        lua.app_data_mut::<Self>()
+            .map(|mut reported| std::mem::take(&mut reported.errors))
+            .unwrap_or_default()
+    }
+}
+
+// how many lines of source are shown around the line of an error
+const CONTEXT: usize = 2;
+
+struct Location {
+    path: String,
+    line: usize,
+    source: Vec<(usize, String)>,
+}
+
+impl Location {
+    // lua formats positions as `chunk:line:`, chunks loaded from a file are named after it
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        for (i, _) in line.match_indices(':') {
+            let (path, tail) = (&line[..i], &line[i + 1..]);
+            let Some((number, _)) = tail.split_once(':') else {
+                continue;
+            };
+            let Ok(number) = number.parse::<usize>() else {
+                continue;
+            };
+            if !Path::new(path).is_file() {
+                continue;
+            }
+            return Some(Self::new(path, number));
+        }
+        None
+    }
+
+    fn new(path: &str, line: usize) -> Self {
+        let source = std::fs::read_to_string(path)
+            .map(|data| {
+                let start = line.saturating_sub(CONTEXT + 1);
+                data.lines()
+                    .enumerate()
+                    .skip(start)
+                    .take(CONTEXT * 2 + 1)
+                    .map(|(i, s)| (i + 1, s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            path: path.to_string(),
+            line,
+            source,
+        }
+    }
+}
+
+struct Entry {
+    hint: &'static str,
+    lines: Vec<String>,
+    location: Option<Location>,
+    // the same error is usually raised every frame
+    count: usize,
+    banner: bool,
+    // how many lines below the header are scrolled past
+    scroll: usize,
+}
+
+impl Entry {
+    // this reads the source of the error, so it's only done for errors that are new
+    fn new(hint: &'static str, lines: Vec<String>, banner: bool) -> Self {
+        Self {
+            hint,
+            location: lines.iter().find_map(|line| Location::parse(line)),
+            lines,
+            count: 1,
+            banner,
+            scroll: 0,
+        }
+    }
+
+    fn lines(err: &mlua::Error) -> Vec<String> {
+        err.to_string()
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.replace('\t', "  "))
+            .collect()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Action {
+    Next,
+    Prev,
+    Dismiss,
+    ScrollUp,
+    ScrollDown,
+}
+
+/// An error panel, shown until the errors are dismissed or fixed
 pub struct Errors {
-    errors: Vec<(&'static str, String)>,
+    entries: Vec<Entry>,
+    selected: usize,
+    next: Keybind,
+    prev: Keybind,
+    dismiss: Keybind,
+    scroll_up: Keybind,
+    scroll_down: Keybind,
+}
+
+impl Default for Errors {
+    fn default() -> Self {
+        Self::new(
+            Keybind::from_char('n').alt(),
+            Keybind::from_char('p').alt(),
+            Keybind::from_char('d').alt(),
+        )
+    }
 }
 
 impl Errors {
+    pub fn new(
+        next: impl Into<Keybind>,
+        prev: impl Into<Keybind>,
+        dismiss: impl Into<Keybind>,
+    ) -> Self {
+        // these have modifiers so they aren't taken from text inputs
+        Self {
+            entries: Vec::new(),
+            selected: 0,
+            next: next.into(),
+            prev: prev.into(),
+            dismiss: dismiss.into(),
+            scroll_up: Keybind::from_char('k').alt(),
+            scroll_down: Keybind::from_char('j').alt(),
+        }
+    }
+
+    /// Sets the keybinds to scroll the selected error, these default to `alt+k` and `alt+j`
+    pub fn scroll_keybinds(mut self, up: impl Into<Keybind>, down: impl Into<Keybind>) -> Self {
+        self.scroll_up = up.into();
+        self.scroll_down = down.into();
+        self
+    }
+
+    /// Shows an error until [`Errors::clear_banner`] is called, e.g. after a failed reload
+    pub fn set_banner(&mut self, hint: &'static str, err: mlua::Error) {
+        self.clear_banner();
+        let entry = Entry::new(hint, Entry::lines(&err), true);
+        self.entries.insert(0, entry);
+        self.selected = 0;
+    }
+
+    pub fn clear_banner(&mut self) {
+        self.entries.retain(|entry| !entry.banner);
+        self.clamp_selected();
+    }
+
+    /// Clears every error, e.g. after a successful reload
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.selected = 0;
+    }
+
+    pub fn collect_reported(&mut self, lua: &mlua::Lua) {
+        for (hint, err) in Reported::take(lua) {
+            self.handle_lua_error(hint, err);
+        }
+    }
+
     pub fn handle_lua_error(&mut self, hint: &'static str, err: mlua::Error) {
-        match err {
-            mlua::Error::SyntaxError { message, .. } => {
-                if let Some(msg) = message.splitn(5, ':').last().map(<str>::trim) {
-                    self.show_error(hint, format!("cannot load configuration: {msg}"))
-                } else {
-                    self.show_error(hint, "configuration has invalid syntax");
-                }
-            }
+        let lines = Entry::lines(&err);
+        if let Some(existing) = self
+            .entries
+            .iter_mut()
+            .find(|e| !e.banner && e.hint == hint && e.lines == lines)
+        {
+            existing.count += 1;
+            return;
+        }
+        self.entries.push(Entry::new(hint, lines, false));
+    }
 
-            mlua::Error::RuntimeError(err) => {
-                if let Some(mut first) = err.lines().nth(0) {
-                    if first.starts_with('[') {
-                        if let Some((_, tail)) = first.rsplit_once(':') {
-                            first = tail.trim()
-                        }
-                    }
-
-                    self.show_error(hint, format!("runtime error: {first}"));
-                }
-                // self.show_error(format!("runtime error: {err}"));
-            }
+    /// Handles the keybinds of the panel, returning whether the event was consumed
+    pub fn event(&mut self, ev: &Event) -> bool {
+        let action = [
+            (self.next, Action::Next),
+            (self.prev, Action::Prev),
+            (self.dismiss, Action::Dismiss),
+            (self.scroll_up, Action::ScrollUp),
+            (self.scroll_down, Action::ScrollDown),
+        ]
+        .into_iter()
+        .find_map(|(keybind, action)| ev.is_keybind_pressed(keybind).then_some(action));
 
-            mlua::Error::BadArgument {
-                to: Some(to),
-                name: Some(name),
-                ..
-            } => {
-                self.show_error(hint, format!("bad argument: {to} w/ {name}"));
+        action.is_some_and(|action| self.apply(action))
+    }
+
+    fn apply(&mut self, action: Action) -> bool {
+        if self.entries.is_empty() {
+            return false;
+        }
+
+        let len = self.entries.len();
+        match action {
+            Action::Next => self.selected = (self.selected + 1) % len,
+            Action::Prev => self.selected = self.selected.checked_sub(1).unwrap_or(len - 1),
+            Action::Dismiss => {
+                self.entries.remove(self.selected);
+                self.clamp_selected();
+            }
+            // scrolling past the end is clamped when rendering, once the height is known
+            Action::ScrollUp => {
+                let entry = &mut self.entries[self.selected];
+                entry.scroll = entry.scroll.saturating_sub(1);
             }
+            Action::ScrollDown => self.entries[self.selected].scroll += 1,
+        }
+        true
+    }
+
+    fn clamp_selected(&mut self) {
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+    }
+
+    pub fn render(&mut self, palette: &Palette, surface: &mut Surface) {
+        let (index, total) = (self.selected + 1, self.entries.len());
+        let Some(entry) = self.entries.get_mut(self.selected) else {
+            return;
+        };
+
+        // the message comes first, then the source where it happened, then the traceback
+        let mut message = entry
+            .lines
+            .iter()
+            .map(|line| (line.clone(), palette.danger));
+        let mut lines: Vec<(String, Rgba)> = message.next().into_iter().collect();
 
-            mlua::Error::CallbackError { cause, .. } => {
-                self.show_error(hint, format!("{cause}"));
+        if let Some(location) = &entry.location {
+            let width = location
+                .source
+                .last()
+                .map_or(1, |(n, _)| n.to_string().len());
+            for (n, line) in &location.source {
+                let fg = if *n == location.line {
+                    palette.danger
+                } else {
+                    palette.foreground
+                };
+                lines.push((format!("{n:>width$} | {line}"), fg));
             }
+        }
+        lines.extend(message);
+
+        // the panel takes at most half of the screen, the header always stays visible
+        let size = surface.rect().size();
+        let height = ((size.y / 2).max(2) - 1) as usize;
+        entry.scroll = entry.scroll.min(lines.len().saturating_sub(height));
 
-            _ => {
-                self.show_error(hint, "cannot load configuration");
+        let mut header = format!("{} ({index}/{total})", entry.hint);
+        if entry.count > 1 {
+            header.push_str(&format!(" x{}", entry.count));
+        }
+        if let Some(location) = &entry.location {
+            header.push_str(&format!(" at {}:{}", location.path, location.line));
+        }
+        if lines.len() > height {
+            let end = (entry.scroll + height).min(lines.len());
+            header.push_str(&format!(" [{}-{end} of {}]", entry.scroll + 1, lines.len()));
+        }
+
+        let visible = lines.into_iter().skip(entry.scroll).take(height);
+        let lines = std::iter::once((header, palette.warning)).chain(visible);
+
+        for (y, (line, fg)) in lines.enumerate() {
+            let y = y as i32;
+            for x in 0..size.x {
+                surface.set(pos2(x, y), Pixel::new(' ').bg(palette.surface));
             }
+            surface.set(pos2(0, y), Grapheme::new(line).fg(fg).bg(palette.surface));
         }
     }
+}
 
-    fn show_error(&mut self, hint: &'static str, err: impl ToString) {
-        self.errors.extend(
-            err.to_string()
-                .lines()
-                .filter(|c| !c.is_empty())
-                .map(|s| (hint, s.to_string())),
-        );
+#[cfg(test)]
+mod tests {
+    use too::{RunConfig, math::vec2};
+
+    use super::*;
+
+    fn err(msg: &str) -> mlua::Error {
+        mlua::Error::runtime(msg)
     }
 
-    pub fn render(&mut self, surface: &mut too::renderer::Surface) {
-        if self.errors.is_empty() {
-            return;
+    fn hints(errors: &Errors) -> Vec<&'static str> {
+        errors.entries.iter().map(|entry| entry.hint).collect()
+    }
+
+    #[test]
+    fn banner_replaces_the_previous_banner() {
+        let mut errors = Errors::default();
+        errors.handle_lua_error("on_frame", err("a"));
+        errors.set_banner("cannot load", err("b"));
+        errors.set_banner("cannot reload", err("c"));
+        assert_eq!(hints(&errors), ["cannot reload", "on_frame"]);
+        assert_eq!(errors.selected, 0);
+
+        errors.clear_banner();
+        assert_eq!(hints(&errors), ["on_frame"]);
+    }
+
+    #[test]
+    fn repeated_errors_are_counted() {
+        let mut errors = Errors::default();
+        for _ in 0..3 {
+            errors.handle_lua_error("on_frame", err("a"));
         }
+        errors.handle_lua_error("callback", err("a"));
+        assert_eq!(hints(&errors), ["on_frame", "callback"]);
+        assert_eq!(errors.entries[0].count, 3);
+    }
 
-        for (y, (hint, error)) in self.errors.drain(..).enumerate() {
-            surface.set(pos2(0, y as i32), Grapheme::new(hint).fg("#F0F").bg("#000"));
+    #[test]
+    fn navigate_and_dismiss() {
+        let mut errors = Errors::default();
+        assert!(!errors.apply(Action::Next));
 
-            surface.set(
-                pos2(hint.len() as i32 + 1, y as i32),
-                Grapheme::new(error).fg("#F00").bg("#000"),
-            );
+        for hint in ["a", "b", "c"] {
+            errors.handle_lua_error(hint, err(hint));
         }
+
+        assert!(errors.apply(Action::Prev));
+        assert_eq!(errors.selected, 2);
+        assert!(errors.apply(Action::Next));
+        assert_eq!(errors.selected, 0);
+        assert!(errors.apply(Action::Next));
+        assert_eq!(errors.selected, 1);
+
+        assert!(errors.apply(Action::Dismiss));
+        assert_eq!(hints(&errors), ["a", "c"]);
+        assert_eq!(errors.selected, 1);
+
+        // dismissing the last entry selects the one before it
+        assert!(errors.apply(Action::Dismiss));
+        assert_eq!(hints(&errors), ["a"]);
+        assert_eq!(errors.selected, 0);
+
+        assert!(errors.apply(Action::Dismiss));
+        assert!(errors.entries.is_empty());
+        assert!(!errors.apply(Action::Dismiss));
+    }
+
+    #[test]
+    fn scrolling_is_clamped_to_the_lines() {
+        let message = (0..20)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut errors = Errors::default();
+        errors.handle_lua_error("callback", err(&message));
+
+        assert!(errors.apply(Action::ScrollUp));
+        assert_eq!(errors.entries[0].scroll, 0);
+        for _ in 0..100 {
+            errors.apply(Action::ScrollDown);
+        }
+
+        // 10 rows are half of the screen, one of them is the header
+        let mut surface = Surface::new(vec2(40, 20));
+        errors.render(&RunConfig::default().palette, &mut surface);
+        assert_eq!(errors.entries[0].scroll, 20 - 9);
+
+        assert!(errors.apply(Action::ScrollUp));
+        assert_eq!(errors.entries[0].scroll, 20 - 10);
     }
 }
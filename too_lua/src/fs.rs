@@ -0,0 +1,109 @@
+use std::time::UNIX_EPOCH;
+
+use anno_lua::Anno;
+use mlua::UserData;
+
+use crate::{Register, runtime::RunningTasks};
+
+pub struct Fs;
+
+impl Register for Fs {
+    const NAME: &'static str = "Fs";
+}
+
+impl Anno for Fs {
+    fn lua_type() -> anno_lua::Type {
+        anno_lua::Type::Class(anno_lua::Class {
+            exact: true,
+            docs: &["Async filesystem access"],
+            name: "Fs",
+            fields: &[
+                anno_lua::Field {
+                    name: "read",
+                    ty: "fun(path: string): string",
+                    docs: &["reads the contents of a file"],
+                },
+                anno_lua::Field {
+                    name: "write",
+                    ty: "fun(path: string, data: string): nil",
+                    docs: &["writes data to a file, replacing its contents"],
+                },
+                anno_lua::Field {
+                    name: "list",
+                    ty: "fun(path: string): string[]",
+                    docs: &["lists the names of the entries in a directory"],
+                },
+                anno_lua::Field {
+                    name: "metadata",
+                    ty: "fun(path: string): { is_file: boolean, is_dir: boolean, len: integer, modified: number? }",
+                    docs: &[
+                        "gets the metadata of a path",
+                        "",
+                        "`modified` is in seconds since the unix epoch",
+                    ],
+                },
+                anno_lua::Field {
+                    name: "watch",
+                    ty: "fun(path: string, task: fun(path: string): nil): integer",
+                    docs: &[
+                        "calls a function, between frames, when a file or anything in a directory changes",
+                        "",
+                        "this returns an id that you can use to stop watching with `Runtime.stop`",
+                    ],
+                },
+            ],
+        })
+    }
+}
+
+impl UserData for Fs {
+    fn add_methods<M>(methods: &mut M)
+    where
+        M: mlua::UserDataMethods<Self>,
+    {
+        methods.add_async_function("read", |lua, path: String| async move {
+            let data = tokio::fs::read(&path).await?;
+            lua.create_string(data)
+        });
+
+        methods.add_async_function(
+            "write",
+            |_lua, (path, data): (String, mlua::String)| async move {
+                let data = data.as_bytes().to_vec();
+                tokio::fs::write(&path, data).await?;
+                Ok(())
+            },
+        );
+
+        methods.add_async_function("list", |_lua, path: String| async move {
+            let mut entries = tokio::fs::read_dir(&path).await?;
+            let mut names = vec![];
+            while let Some(entry) = entries.next_entry().await? {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+            names.sort();
+            Ok(names)
+        });
+
+        methods.add_async_function("metadata", |lua, path: String| async move {
+            let md = tokio::fs::metadata(&path).await?;
+            let modified = md
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|dur| dur.as_secs_f64());
+
+            let table = lua.create_table()?;
+            table.set("is_file", md.is_file())?;
+            table.set("is_dir", md.is_dir())?;
+            table.set("len", md.len())?;
+            table.set("modified", modified)?;
+            Ok(table)
+        });
+
+        methods.add_function("watch", |lua, (path, task): (String, mlua::Function)| {
+            let mut tasks = lua.app_data_mut::<RunningTasks>().unwrap();
+            Ok(tasks.push_watch(path, task))
+        });
+    }
+}
@@ -1,6 +1,6 @@
 use std::{collections::HashSet, path::Path};
 
-use crate::{Arguments, Bindings};
+use crate::{Arguments, Bindings, Register as _, bindings::Value};
 
 pub fn write_annotations(file: impl AsRef<Path>, bindings: &Bindings) -> std::io::Result<()> {
     use std::io::Write as _;
@@ -44,11 +44,64 @@ pub fn generate(bindings: &Bindings) -> String {
     );
     _ = writeln!(&mut out);
 
+    _ = writeln!(
+        &mut out,
+        "--- builds children with a function, and builds them again when a `Value` read by it changes\n\
+        ---@param builder fun(ui: ui): nil\n\
+        ---@diagnostic disable-next-line: lowercase-global\n\
+        function reactive(builder) end"
+    );
+    _ = writeln!(&mut out);
+
+    _ = writeln!(
+        &mut out,
+        "--- what a script returns, either a `view` function or a table of these hooks\n\
+        ---@class (exact) Hooks\n\
+        --- the version of the script, given to the next script's `on_reload`\n\
+        ---@field version integer?\n\
+        --- builds the ui\n\
+        ---@field view fun(ui: ui, data: any?): nil\n\
+        --- called once, before `view`, the first time the script loads\n\
+        ---@field init (fun(data: any?): nil)?\n\
+        --- called every frame with the time since the last frame, in seconds\n\
+        ---@field on_frame (fun(dt: number): nil)?\n\
+        --- called with the size of the screen when it changes, and after the script loads\n\
+        ---@field on_resize (fun(width: integer, height: integer): nil)?\n\
+        --- called before the script is replaced, what it returns is given to the next script's `on_reload`\n\
+        ---@field save (fun(): any)?\n\
+        --- called instead of `init` when the script is reloaded, with what the previous script's `save`\n\
+        --- returned and its `version`. if `save` failed, the state is nil\n\
+        ---@field on_reload (fun(state: any, version: integer?): nil)?\n\
+        --- called when the application exits\n\
+        ---@field on_exit (fun(): nil)?"
+    );
+    _ = writeln!(&mut out);
+
     // we can't sort the proxies because their lexical order is important
     for proxy in &bindings.proxies {
         _ = anno_lua::generate_type(&mut out, &(proxy.ty)());
     }
 
+    // anno_lua has no way to describe operators, so they extend the class here
+    if bindings
+        .proxies
+        .iter()
+        .any(|proxy| proxy.name == Value::NAME)
+    {
+        _ = writeln!(
+            &mut out,
+            "--- integer arithmetic saturates instead of overflowing, like `Value:inc`\n\
+            ---@class Value\n\
+            ---@operator add(number|Value): number\n\
+            ---@operator sub(number|Value): number\n\
+            ---@operator mul(number|Value): number\n\
+            ---@operator div(number|Value): number\n\
+            ---@operator unm: number\n\
+            ---@operator concat(string|number|Value): string"
+        );
+        _ = writeln!(&mut out);
+    }
+
     let mut bindings = bindings.bindings.clone();
     bindings.sort_by_cached_key(|(spec, _)| spec.name);
     bindings.dedup_by_key(|(spec, _)| spec.name);
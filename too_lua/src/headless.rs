@@ -0,0 +1,471 @@
+use std::{path::PathBuf, time::Duration};
+
+use mlua::AnyUserData;
+use too::{
+    RunConfig,
+    backend::Event,
+    math::{Pos2, Vec2, pos2, vec2},
+    renderer::{Attribute, Renderer, Rgba, Surface},
+    view::State,
+};
+
+use crate::{
+    Bindings, Channel, FileLoader, Loader, Mapping, MemoryLoader, Sandbox, Script, Tree, TreeDump,
+    application::{init_lua, render_tree},
+    bindings::Value,
+    budget::Budget,
+    channel::Channels,
+    errors::Reported,
+    reactive::{Changes, OnChange},
+    runtime::{Clock, RunningTasks},
+};
+
+/// Runs a script without a terminal, rendering into an in-memory surface
+pub struct Headless {
+    loader: Box<dyn Loader>,
+    size: Vec2,
+    config: RunConfig,
+    bindings: Bindings,
+    channels: Vec<Channel>,
+    budget: Option<Duration>,
+    sandbox: Option<Sandbox>,
+    on_change: Vec<(String, OnChange)>,
+}
+
+impl Headless {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self::from_loader(FileLoader::new(path))
+    }
+
+    pub fn from_source(source: impl ToString) -> Self {
+        Self::from_loader(MemoryLoader::new("source", source.to_string()))
+    }
+
+    pub fn from_loader(loader: impl Loader) -> Self {
+        Self {
+            loader: Box::new(loader),
+            size: vec2(80, 24),
+            config: RunConfig::default(),
+            bindings: Bindings::default_bindings(),
+            channels: Vec::new(),
+            budget: None,
+            sandbox: None,
+            on_change: Vec::new(),
+        }
+    }
+
+    pub fn size(mut self, size: Vec2) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn config(mut self, config: RunConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_bindings(mut self, bindings: Bindings) -> Self {
+        self.bindings = bindings;
+        self
+    }
+
+    pub fn channel(mut self, channel: Channel) -> Self {
+        self.channels.push(channel);
+        self
+    }
+
+    pub fn budget(mut self, budget: Duration) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Calls `on_change` with the old and new value, after the frame in which
+    /// the persisted value `id` changed
+    pub fn on_value_change(
+        mut self,
+        id: impl Into<String>,
+        on_change: impl FnMut(&Value, &Value) + Send + 'static,
+    ) -> Self {
+        self.on_change.push((id.into(), Box::new(on_change)));
+        self
+    }
+
+    /// Loads the script
+    ///
+    /// The harness has its own single-threaded tokio runtime, so this shouldn't be
+    /// called from inside another runtime. Spawned tasks make progress on each frame
+    pub fn start(self) -> mlua::Result<Harness> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(mlua::Error::external)?;
+        let guard = runtime.enter();
+
+        let lua = init_lua(&self.bindings, self.sandbox.as_ref())?;
+        lua.set_app_data(self.config.palette);
+        lua.set_app_data(Channels::new(self.channels));
+        lua.set_app_data(Budget::new(self.budget));
+        // timers follow the elapsed time given as input, rather than the system time
+        Clock::manual(&lua);
+        for (id, on_change) in self.on_change {
+            Changes::on_host_change(&lua, id, on_change);
+        }
+
+        let script = Script::from_loader(self.loader, None, &lua)?;
+        script.resize(&lua, self.size)?;
+        drop(guard);
+
+        Ok(Harness {
+            mapping: Mapping::from_bindings(self.bindings),
+            state: State::new(self.config.palette, self.config.animation),
+            surface: Surface::new(self.size),
+            snapshot: Snapshot::new(self.size),
+            errors: Vec::new(),
+            script,
+            lua,
+            runtime,
+        })
+    }
+}
+
+/// A loaded script that can be rendered one frame at a time
+pub struct Harness {
+    lua: mlua::Lua,
+    script: Script,
+    mapping: Mapping,
+    state: State,
+    surface: Surface,
+    snapshot: Snapshot,
+    errors: Vec<mlua::Error>,
+    // this is dropped last, the tasks it owns can hold on to lua values
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Harness {
+    pub fn lua(&self) -> &mlua::Lua {
+        &self.lua
+    }
+
+    /// Enters the harness's tokio runtime
+    ///
+    /// This is needed to spawn tasks when calling into [`Harness::lua`] directly
+    pub fn enter(&self) -> tokio::runtime::EnterGuard<'_> {
+        self.runtime.enter()
+    }
+
+    pub fn script(&self) -> &Script {
+        &self.script
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.surface.rect().size()
+    }
+
+    /// Reloads the script from its loader, see [`Script::reload`]
+    ///
+    /// If this fails, the previous script and its tree are kept.
+    /// Like the application, the new script is told the size of the screen
+    pub fn reload(&mut self) -> mlua::Result<()> {
+        let _guard = self.runtime.enter();
+        self.script.reload(&self.lua)?;
+        self.script.resize(&self.lua, self.size())
+    }
+
+    pub fn render(&mut self) -> std::io::Result<&Snapshot> {
+        self.frame(Duration::ZERO)
+    }
+
+    /// Applies an input and renders the frame that observes it
+    pub fn input(&mut self, input: impl Into<Input>) -> std::io::Result<&Snapshot> {
+        match input.into() {
+            Input::Event(ev) => {
+                if let Event::Resize(size) = ev {
+                    let _guard = self.runtime.enter();
+                    self.snapshot = Snapshot::new(size);
+                    self.errors
+                        .extend(self.script.resize(&self.lua, size).err());
+                }
+                self.surface.update(&ev);
+                self.state.event(&ev);
+                self.frame(Duration::ZERO)
+            }
+            Input::Elapsed(dt) => self.frame(dt),
+        }
+    }
+
+    /// Applies each input in order, calling `step` with the index of the input after its frame
+    pub fn replay<I>(
+        &mut self,
+        inputs: impl IntoIterator<Item = I>,
+        mut step: impl FnMut(usize, &Self),
+    ) -> std::io::Result<()>
+    where
+        I: Into<Input>,
+    {
+        for (i, input) in inputs.into_iter().enumerate() {
+            self.input(input)?;
+            step(i, self);
+        }
+        Ok(())
+    }
+
+    pub fn dump(&self) -> TreeDump {
+        TreeDump::build(&self.lua.app_data_ref::<Tree>().unwrap())
+    }
+
+    /// Gets a copy of the persisted [`Value`] for `id`
+    pub fn value(&self, id: &str) -> Option<Value> {
+        let table = self
+            .lua
+            .globals()
+            .get::<mlua::Table>(Value::GLOBAL_KEY)
+            .ok()?;
+        let value = table.get::<AnyUserData>(id).ok()?;
+        value.borrow::<Value>().ok().map(|value| value.clone())
+    }
+
+    /// Sets the persisted [`Value`] for `id`, returning whether it exists
+    ///
+    /// This is picked up by reactive nodes on the next frame
+    pub fn set_value(&self, id: &str, value: impl Into<Value>) -> mlua::Result<bool> {
+        let Ok(table) = self.lua.globals().get::<mlua::Table>(Value::GLOBAL_KEY) else {
+            return Ok(false);
+        };
+        let Ok(data) = table.get::<AnyUserData>(id) else {
+            return Ok(false);
+        };
+        Value::update(&self.lua, &data, |this| *this = value.into())?;
+        Ok(true)
+    }
+
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    /// Calls the `on_exit` hook of the script
+    pub fn exit(self) -> mlua::Result<()> {
+        let _guard = self.runtime.enter();
+        self.script.exit(&self.lua)
+    }
+
+    /// Takes the errors raised by callbacks since the last call
+    pub fn take_errors(&mut self) -> Vec<mlua::Error> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn frame(&mut self, elapsed: Duration) -> std::io::Result<&Snapshot> {
+        let dt = elapsed.as_secs_f32();
+        let _guard = self.runtime.enter();
+        // let the tasks that are ready run, so they finish between frames like they would in the app
+        self.runtime.block_on(tokio::task::yield_now());
+
+        self.state.update(dt);
+        self.lua.set_app_data(*self.state.palette());
+        Clock::advance(&self.lua, elapsed);
+
+        self.errors.extend(self.script.frame(&self.lua, dt).err());
+        self.errors.extend(RunningTasks::run_callbacks(&self.lua));
+        self.errors.extend(
+            RunningTasks::take_failures(&self.lua)
+                .into_iter()
+                .map(|(_, err)| err),
+        );
+        render_tree(&self.lua, &self.mapping, &mut self.state, &mut self.surface);
+        self.errors
+            .extend(Reported::take(&self.lua).into_iter().map(|(_, err)| err));
+
+        self.surface
+            .render(&mut SnapshotRenderer::new(&mut self.snapshot))?;
+
+        self.errors.extend(Changes::notify(&self.lua));
+        Ok(&self.snapshot)
+    }
+}
+
+pub enum Input {
+    Event(Event),
+    Elapsed(Duration),
+}
+
+impl From<Event> for Input {
+    fn from(value: Event) -> Self {
+        Self::Event(value)
+    }
+}
+
+impl From<Duration> for Input {
+    fn from(value: Duration) -> Self {
+        Self::Elapsed(value)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SnapshotCell {
+    pub ch: char,
+    pub fg: Option<Rgba>,
+    pub bg: Option<Rgba>,
+    pub attr: Option<Attribute>,
+}
+
+impl Default for SnapshotCell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            fg: None,
+            bg: None,
+            attr: None,
+        }
+    }
+}
+
+/// The rendered cells of a [`Harness`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    size: Vec2,
+    cells: Vec<SnapshotCell>,
+}
+
+impl Snapshot {
+    fn new(size: Vec2) -> Self {
+        Self {
+            size,
+            cells: vec![SnapshotCell::default(); (size.x * size.y).max(0) as usize],
+        }
+    }
+
+    pub const fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    pub fn get(&self, pos: Pos2) -> Option<&SnapshotCell> {
+        self.index(pos).map(|index| &self.cells[index])
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[SnapshotCell]> + '_ {
+        self.cells.chunks(self.size.x.max(1) as usize)
+    }
+
+    /// Renders the cells as text, with trailing whitespace trimmed from each row
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        for row in self.rows() {
+            let line = row.iter().map(|cell| cell.ch).collect::<String>();
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    fn index(&self, pos: Pos2) -> Option<usize> {
+        if pos.x < 0 || pos.y < 0 || pos.x >= self.size.x || pos.y >= self.size.y {
+            return None;
+        }
+        Some((pos.y * self.size.x + pos.x) as usize)
+    }
+}
+
+impl std::fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+struct SnapshotRenderer<'a> {
+    snapshot: &'a mut Snapshot,
+    cursor: Pos2,
+    fg: Option<Rgba>,
+    bg: Option<Rgba>,
+    attr: Option<Attribute>,
+}
+
+impl<'a> SnapshotRenderer<'a> {
+    fn new(snapshot: &'a mut Snapshot) -> Self {
+        Self {
+            snapshot,
+            cursor: pos2(0, 0),
+            fg: None,
+            bg: None,
+            attr: None,
+        }
+    }
+}
+
+impl Renderer for SnapshotRenderer<'_> {
+    fn begin(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn end(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn clear_screen(&mut self) -> std::io::Result<()> {
+        self.snapshot.cells.fill(SnapshotCell::default());
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn move_to(&mut self, pos: Pos2) -> std::io::Result<()> {
+        self.cursor = pos;
+        Ok(())
+    }
+
+    fn write(&mut self, ch: char) -> std::io::Result<()> {
+        if let Some(index) = self.snapshot.index(self.cursor) {
+            self.snapshot.cells[index] = SnapshotCell {
+                ch,
+                fg: self.fg,
+                bg: self.bg,
+                attr: self.attr,
+            };
+        }
+        self.cursor.x += 1;
+        Ok(())
+    }
+
+    fn write_str(&mut self, data: &str) -> std::io::Result<()> {
+        data.chars().try_for_each(|ch| self.write(ch))
+    }
+
+    fn set_fg(&mut self, color: Rgba) -> std::io::Result<()> {
+        self.fg = Some(color);
+        Ok(())
+    }
+
+    fn set_bg(&mut self, color: Rgba) -> std::io::Result<()> {
+        self.bg = Some(color);
+        Ok(())
+    }
+
+    fn reset_fg(&mut self) -> std::io::Result<()> {
+        self.fg = None;
+        Ok(())
+    }
+
+    fn reset_bg(&mut self) -> std::io::Result<()> {
+        self.bg = None;
+        Ok(())
+    }
+
+    fn set_attr(&mut self, attr: Attribute) -> std::io::Result<()> {
+        self.attr = Some(attr);
+        Ok(())
+    }
+
+    fn reset_attr(&mut self) -> std::io::Result<()> {
+        self.attr = None;
+        Ok(())
+    }
+}
@@ -10,8 +10,30 @@ pub use application::Application;
 mod script;
 pub use script::Script;
 
+mod loader;
+pub use loader::{FileLoader, Loader, MemoryLoader};
+
+mod headless;
+pub use headless::{Harness, Headless, Input, Snapshot, SnapshotCell};
+
 mod runtime;
 
+mod channel;
+pub use channel::Channel;
+
+mod process;
+
+mod fs;
+
+mod watcher;
+
+mod budget;
+
+mod reactive;
+
+mod sandbox;
+pub use sandbox::Sandbox;
+
 #[macro_use]
 mod mapping;
 pub use mapping::{Context, Indirect, Mapping};
@@ -20,6 +42,12 @@ mod tree;
 pub use tree::{DebugNode, Tree};
 use tree::{LuaId, Node, UiBuilder};
 
+mod dump;
+pub use dump::{NodeDump, TreeDump};
+
+mod diff;
+pub use diff::{TreeChange, TreeDiff};
+
 mod errors;
 use errors::Errors;
 
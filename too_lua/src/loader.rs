@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+
+/// Where the source of a script comes from
+///
+/// A script is loaded again from its loader whenever it is reloaded
+pub trait Loader: Send + 'static {
+    /// A name for the script, this is what is shown in error messages
+    fn name(&self) -> String;
+
+    /// Loads the source of the script
+    fn load(&self) -> std::io::Result<String>;
+
+    /// The file the script was loaded from, if it is on disk
+    ///
+    /// If this is provided, the file is watched for changes
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+}
+
+impl<T: Loader + ?Sized> Loader for Box<T> {
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn load(&self) -> std::io::Result<String> {
+        (**self).load()
+    }
+
+    fn path(&self) -> Option<&Path> {
+        (**self).path()
+    }
+}
+
+/// Loads a script from a file
+#[derive(Clone, Debug)]
+pub struct FileLoader {
+    path: PathBuf,
+}
+
+impl FileLoader {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Loader for FileLoader {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn load(&self) -> std::io::Result<String> {
+        std::fs::read_to_string(&self.path)
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+}
+
+/// Loads a script from memory, e.g. one embedded with `include_str!`
+#[derive(Clone, Debug)]
+pub struct MemoryLoader {
+    name: String,
+    source: String,
+}
+
+impl MemoryLoader {
+    pub fn new(name: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: source.into(),
+        }
+    }
+}
+
+impl Loader for MemoryLoader {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn load(&self) -> std::io::Result<String> {
+        Ok(self.source.clone())
+    }
+}
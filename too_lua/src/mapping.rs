@@ -45,7 +45,8 @@ impl Mapping {
 
     #[inline(always)]
     pub fn evaluate(&self, ui: &Ui, ctx: Context<'_>) {
-        if ctx.id == ctx.tree.root {
+        // reactive nodes don't have a view, their children are laid out in their place
+        if ctx.id == ctx.tree.root || ctx.tree.reactives.contains_key(&ctx.id) {
             ctx.visit_children(self, ui);
             return;
         }
@@ -1,7 +1,12 @@
-use mlua::{AnyUserData, FromLua, UserDataRef, UserDataRefMut};
+use mlua::{AnyUserData, FromLua, FromLuaMulti, IntoLuaMulti, UserDataRef};
 use too::view::Ui;
 
-use crate::{LuaId, Node, Tree, bindings::Value};
+use crate::{
+    LuaId, Node, Tree,
+    bindings::{Value, ValueMut},
+    budget::Budget,
+    errors::Reported,
+};
 
 use super::Mapping;
 
@@ -52,7 +57,22 @@ impl<'a> Context<'a> {
         data.borrow::<Value>().ok()
     }
 
-    pub fn value_mut(&self, data: &AnyUserData) -> Option<UserDataRefMut<Value>> {
-        data.borrow_mut::<Value>().ok()
+    /// Borrows a value mutably, changing it rebuilds the reactive nodes that read it
+    pub fn value_mut(&self, data: &AnyUserData) -> Option<ValueMut<'a>> {
+        ValueMut::new(self.lua, data).ok()
+    }
+
+    /// Calls a lua function within the script budget, reporting any error
+    pub fn call<R>(&self, func: &mlua::Function, args: impl IntoLuaMulti) -> Option<R>
+    where
+        R: FromLuaMulti,
+    {
+        match Budget::guard(self.lua, || func.call::<R>(args)) {
+            Ok(out) => Some(out),
+            Err(err) => {
+                Reported::report(self.lua, "handler", err);
+                None
+            }
+        }
     }
 }
@@ -0,0 +1,177 @@
+use std::{collections::HashMap, process::Stdio};
+
+use anno_lua::Anno;
+use mlua::{FromLua, UserData};
+use tokio::io::{AsyncBufReadExt as _, AsyncRead, BufReader};
+
+use crate::{Channel, Register, helper::expect_table, runtime::RunningTasks};
+
+enum Sink {
+    Function(mlua::Function),
+    Channel(Channel),
+}
+
+async fn forward(sink: Option<Sink>, reader: Option<impl AsyncRead + Unpin>) -> mlua::Result<()> {
+    let (Some(sink), Some(reader)) = (sink, reader) else {
+        return Ok(());
+    };
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        match &sink {
+            Sink::Function(func) => func.call_async::<()>(line).await?,
+            Sink::Channel(channel) => _ = channel.send(line),
+        }
+    }
+    Ok(())
+}
+
+impl FromLua for Sink {
+    fn from_lua(value: mlua::Value, lua: &mlua::Lua) -> mlua::Result<Self> {
+        match value {
+            mlua::Value::Function(func) => Ok(Self::Function(func)),
+            value => Channel::from_lua(value, lua).map(Self::Channel),
+        }
+    }
+}
+
+struct ProcessParams {
+    command: String,
+    args: Option<Vec<String>>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    stdout: Option<Sink>,
+    stderr: Option<Sink>,
+    exit: Option<mlua::Function>,
+}
+
+impl FromLua for ProcessParams {
+    fn from_lua(value: mlua::Value, _lua: &mlua::Lua) -> mlua::Result<Self> {
+        expect_table(&value, |table| {
+            Ok(Self {
+                command: table.get("command")?,
+                args: table.get("args")?,
+                cwd: table.get("cwd")?,
+                env: table.get("env")?,
+                stdout: table.get("stdout")?,
+                stderr: table.get("stderr")?,
+                exit: table.get("exit")?,
+            })
+        })
+    }
+}
+
+pub struct Process;
+
+impl Process {
+    async fn run(params: ProcessParams) -> mlua::Result<Option<i32>> {
+        let ProcessParams {
+            command: name,
+            args,
+            cwd,
+            env,
+            stdout,
+            stderr,
+            exit,
+        } = params;
+
+        let mut command = tokio::process::Command::new(&name);
+        command
+            .args(args.iter().flatten())
+            .envs(env.iter().flatten())
+            .stdin(Stdio::null())
+            .stdout(Self::stdio(&stdout))
+            .stderr(Self::stdio(&stderr))
+            // stopping the task drops the child, so this is what kills it
+            .kill_on_drop(true);
+
+        if let Some(cwd) = &cwd {
+            command.current_dir(cwd);
+        }
+
+        let mut child = command
+            .spawn()
+            .map_err(|err| mlua::Error::runtime(format!("cannot spawn {name}: {err}")))?;
+
+        let (stdout, stderr) = tokio::join!(
+            forward(stdout, child.stdout.take()),
+            forward(stderr, child.stderr.take()),
+        );
+        stdout?;
+        stderr?;
+
+        let code = child.wait().await?.code();
+        if let Some(exit) = exit {
+            exit.call_async::<()>(code).await?;
+        }
+        Ok(code)
+    }
+
+    fn stdio(sink: &Option<Sink>) -> Stdio {
+        if sink.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        }
+    }
+}
+
+impl Register for Process {
+    const NAME: &'static str = "Process";
+}
+
+impl Anno for Process {
+    fn lua_type() -> anno_lua::Type {
+        anno_lua::Type::Class(anno_lua::Class {
+            exact: true,
+            docs: &["Runs local processes"],
+            name: "Process",
+            fields: &[anno_lua::Field {
+                name: "spawn",
+                ty: "fun(params: { \
+                    command: string, \
+                    args: string[]?, \
+                    cwd: string?, \
+                    env: table<string, string>?, \
+                    stdout: (fun(line: string): nil)|Channel?, \
+                    stderr: (fun(line: string): nil)|Channel?, \
+                    exit: (fun(code: integer?): nil)? \
+                }): integer",
+                docs: &[
+                    "spawns a process",
+                    "",
+                    "`stdout` and `stderr` receive each line the process writes",
+                    "`exit` is called with the exit code of the process",
+                    "",
+                    "this returns a task id, stopping the task with `Runtime.stop` kills the process",
+                ],
+            }],
+        })
+    }
+}
+
+impl UserData for Process {
+    fn add_methods<M>(methods: &mut M)
+    where
+        M: mlua::UserDataMethods<Self>,
+    {
+        methods.add_function("spawn", |lua, params: ProcessParams| {
+            let (id, report) = lua.app_data_mut::<RunningTasks>().unwrap().reserve();
+
+            let handle = tokio::spawn(async move {
+                let result = Self::run(params).await.map(|code| {
+                    let code = code.map_or(mlua::Value::Nil, |code| {
+                        mlua::Value::Integer(code as mlua::Integer)
+                    });
+                    std::iter::once(code).collect()
+                });
+                report.complete(result)
+            });
+
+            lua.app_data_mut::<RunningTasks>()
+                .unwrap()
+                .push(id, handle.abort_handle());
+            Ok(id)
+        });
+    }
+}
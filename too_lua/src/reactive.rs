@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+
+use mlua::AnyUserData;
+
+use crate::{
+    bindings::{Value, ValueMut},
+    budget::Budget,
+    runtime::RunningTasks,
+};
+
+// computed values and subscriptions are kept in tables with weak keys,
+// so they're dropped along with the value
+const COMPUTED: &str = "__TOO_COMPUTED";
+const ON_CHANGE: &str = "__TOO_ON_CHANGE";
+
+/// Called with the old and the new value, after the frame that changed the value
+pub(crate) type OnChange = Box<dyn FnMut(&Value, &Value) + Send>;
+
+// values are identified by their userdata, so copies made in rust aren't tracked
+fn key(data: &AnyUserData) -> usize {
+    data.to_pointer() as usize
+}
+
+/// Tracks which values are read while building reactive nodes, and which are written
+#[derive(Default)]
+pub(crate) struct Changes {
+    reads: Vec<HashSet<usize>>,
+    dirty: HashSet<usize>,
+    // what each computed value read the last time it was computed
+    computed: HashMap<usize, HashSet<usize>>,
+    // the values changed this frame, with what they were at the start of it
+    changed: HashMap<usize, (AnyUserData, Value)>,
+    // subscriptions from the host, by the id of a persisted value
+    host: Vec<(String, OnChange)>,
+}
+
+impl Changes {
+    pub(crate) fn read(lua: &mlua::Lua, data: &AnyUserData) {
+        let Some(mut this) = lua.app_data_mut::<Self>() else {
+            return;
+        };
+        if let Some(reads) = this.reads.last_mut() {
+            reads.insert(key(data));
+        }
+    }
+
+    pub(crate) fn write(lua: &mlua::Lua, data: &AnyUserData, before: &Value) {
+        if let Some(mut this) = lua.app_data_mut::<Self>() {
+            this.dirty.insert(key(data));
+            this.changed
+                .entry(key(data))
+                .or_insert_with(|| (data.clone(), before.clone()));
+        }
+    }
+
+    /// Subscribes to changes of a value, returning an id that can be given to `Runtime.stop`
+    pub(crate) fn on_change(
+        lua: &mlua::Lua,
+        data: &AnyUserData,
+        func: mlua::Function,
+    ) -> mlua::Result<u64> {
+        let table = Self::weak_table(lua, ON_CHANGE)?;
+        let subscribers = match table.get::<mlua::Table>(data) {
+            Ok(subscribers) => subscribers,
+            Err(..) => {
+                let subscribers = lua.create_table()?;
+                table.set(data, &subscribers)?;
+                subscribers
+            }
+        };
+        let id = RunningTasks::next_id();
+        subscribers.set(id, func)?;
+        Ok(id)
+    }
+
+    pub(crate) fn unsubscribe(lua: &mlua::Lua, id: u64) -> bool {
+        let Ok(table) = lua.named_registry_value::<mlua::Table>(ON_CHANGE) else {
+            return false;
+        };
+        for (_, subscribers) in table.pairs::<AnyUserData, mlua::Table>().flatten() {
+            if subscribers.contains_key(id).unwrap_or(false) {
+                return subscribers.set(id, mlua::Value::Nil).is_ok();
+            }
+        }
+        false
+    }
+
+    /// Takes the subscriptions made from lua, so a reload can start without them
+    pub(crate) fn take_subscriptions(lua: &mlua::Lua) -> Option<mlua::Table> {
+        let table = lua.named_registry_value::<mlua::Table>(ON_CHANGE).ok();
+        let _ = lua.set_named_registry_value(ON_CHANGE, mlua::Value::Nil);
+        table
+    }
+
+    /// Puts back subscriptions taken by [`Changes::take_subscriptions`], after a failed reload
+    pub(crate) fn restore_subscriptions(lua: &mlua::Lua, table: Option<mlua::Table>) {
+        let _ = lua.set_named_registry_value(ON_CHANGE, table);
+    }
+
+    pub(crate) fn on_host_change(lua: &mlua::Lua, id: String, func: OnChange) {
+        if let Some(mut this) = lua.app_data_mut::<Self>() {
+            this.host.push((id, func));
+        }
+    }
+
+    /// Calls the subscriptions for the values changed since the last call
+    #[profiling::function]
+    pub(crate) fn notify(lua: &mlua::Lua) -> Vec<mlua::Error> {
+        let (changed, mut host) = match lua.app_data_mut::<Self>() {
+            Some(mut this) if !this.changed.is_empty() => (
+                std::mem::take(&mut this.changed),
+                std::mem::take(&mut this.host),
+            ),
+            _ => return vec![],
+        };
+
+        let mut errors = vec![];
+        let subscribers = lua.named_registry_value::<mlua::Table>(ON_CHANGE).ok();
+        let persisted = lua.globals().get::<mlua::Table>(Value::GLOBAL_KEY).ok();
+
+        for (key, (data, old)) in &changed {
+            let Ok(new) = data.borrow::<Value>().map(|value| value.clone()) else {
+                continue;
+            };
+            // it may have been changed back within the frame
+            if new == *old {
+                continue;
+            }
+
+            for (id, func) in &mut host {
+                let is_same = persisted
+                    .as_ref()
+                    .and_then(|table| table.get::<AnyUserData>(id.as_str()).ok())
+                    .is_some_and(|data| self::key(&data) == *key);
+                if is_same {
+                    func(old, &new);
+                }
+            }
+
+            let Some(funcs) = subscribers
+                .as_ref()
+                .and_then(|table| table.get::<mlua::Table>(data).ok())
+            else {
+                continue;
+            };
+
+            // subscribers are called in the order they subscribed
+            let mut funcs = funcs
+                .pairs::<u64, mlua::Function>()
+                .flatten()
+                .collect::<Vec<_>>();
+            funcs.sort_by_key(|&(id, _)| id);
+
+            for (_, func) in funcs {
+                let result = Budget::guard(lua, || {
+                    func.call::<()>((old.to_lua_value(lua)?, new.to_lua_value(lua)?))
+                });
+                errors.extend(result.err());
+            }
+        }
+
+        if let Some(mut this) = lua.app_data_mut::<Self>() {
+            // anything subscribed while notifying is kept
+            host.append(&mut this.host);
+            this.host = host;
+        }
+        errors
+    }
+
+    fn weak_table(lua: &mlua::Lua, name: &str) -> mlua::Result<mlua::Table> {
+        if let Ok(table) = lua.named_registry_value::<mlua::Table>(name) {
+            return Ok(table);
+        }
+
+        let table = lua.create_table()?;
+        let mt = lua.create_table()?;
+        mt.set("__mode", "k")?;
+        table.set_metatable(Some(mt));
+        lua.set_named_registry_value(name, &table)?;
+        Ok(table)
+    }
+
+    /// Runs `f`, returning the values that were read by it
+    ///
+    /// Reads made by a nested call are only reported to the nested call
+    pub(crate) fn track<R>(lua: &mlua::Lua, f: impl FnOnce() -> R) -> (R, HashSet<usize>) {
+        if let Some(mut this) = lua.app_data_mut::<Self>() {
+            this.reads.push(HashSet::new());
+        }
+        let out = f();
+        let reads = lua
+            .app_data_mut::<Self>()
+            .and_then(|mut this| this.reads.pop())
+            .unwrap_or_default();
+        (out, reads)
+    }
+
+    /// Creates a value that is computed by `compute`, and recomputed when what it read changes
+    pub(crate) fn computed(lua: &mlua::Lua, compute: mlua::Function) -> mlua::Result<AnyUserData> {
+        let (value, reads) = Self::track(lua, || compute.call::<mlua::Value>(()));
+        let data = lua.create_userdata(Value::from_lua_value(value?)?)?;
+
+        let table = Self::weak_table(lua, COMPUTED)?;
+        table.set(&data, compute)?;
+
+        let live = table
+            .pairs::<AnyUserData, mlua::Value>()
+            .flatten()
+            .map(|(data, _)| key(&data))
+            .collect::<HashSet<_>>();
+
+        if let Some(mut this) = lua.app_data_mut::<Self>() {
+            this.computed.retain(|key, _| live.contains(key));
+            this.computed.insert(key(&data), reads);
+        }
+        Ok(data)
+    }
+
+    /// Recomputes the computed values that read a value that has changed
+    ///
+    /// Recomputing a value that changed can make values computed from it stale too,
+    /// so this repeats until nothing new has changed.
+    /// A failing value doesn't stop the others, the errors are returned together
+    #[profiling::function]
+    pub(crate) fn recompute(lua: &mlua::Lua) -> Vec<mlua::Error> {
+        let Ok(table) = lua.named_registry_value::<mlua::Table>(COMPUTED) else {
+            return Vec::new();
+        };
+
+        let mut errors = Vec::new();
+        let mut seen = HashSet::new();
+        loop {
+            let stale = {
+                let Some(this) = lua.app_data_ref::<Self>() else {
+                    return errors;
+                };
+
+                let fresh = this
+                    .dirty
+                    .difference(&seen)
+                    .copied()
+                    .collect::<HashSet<_>>();
+                if fresh.is_empty() {
+                    return errors;
+                }
+                seen.extend(&fresh);
+
+                table
+                    .pairs::<AnyUserData, mlua::Function>()
+                    .flatten()
+                    .filter(|(data, _)| {
+                        this.computed
+                            .get(&key(data))
+                            .is_some_and(|reads| !reads.is_disjoint(&fresh))
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            for (data, compute) in stale {
+                let (value, reads) = Self::track(lua, || compute.call::<mlua::Value>(()));
+                if let Some(mut this) = lua.app_data_mut::<Self>() {
+                    this.computed.insert(key(&data), reads);
+                }
+                let result = value.and_then(Value::from_lua_value).and_then(|value| {
+                    *ValueMut::new(lua, &data)? = value;
+                    Ok(())
+                });
+                if let Err(err) = result {
+                    errors.push(err);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn take_dirty(lua: &mlua::Lua) -> HashSet<usize> {
+        lua.app_data_mut::<Self>()
+            .map(|mut this| std::mem::take(&mut this.dirty))
+            .unwrap_or_default()
+    }
+}
@@ -1,9 +1,18 @@
-use std::{collections::HashMap, sync::atomic::AtomicU64};
+use std::{
+    collections::HashMap,
+    sync::{Arc, atomic::AtomicU64, mpsc},
+    time::Instant,
+};
 
 use anno_lua::Anno;
 use mlua::{FromLua, UserData};
 
-use crate::Register;
+use crate::{
+    Register,
+    budget::Budget,
+    reactive::Changes,
+    watcher::{WatchHandle, Watched, watch_for_changes},
+};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Duration {
@@ -111,10 +120,46 @@ impl Anno for Runtime {
                         "this returns an id that you can use to stop the task",
                     ],
                 },
+                anno_lua::Field {
+                    name: "status",
+                    ty: "fun(id: integer): \"running\"|\"finished\"|\"failed\"|\"stopped\"|nil",
+                    docs: &[
+                        "gets the status of a task, or nil if the task is unknown",
+                        "",
+                        "tasks are forgotten once they're joined, or once there are many newer ones",
+                    ],
+                },
+                anno_lua::Field {
+                    name: "join",
+                    ty: "fun(id: integer): any",
+                    docs: &[
+                        "waits for a task to complete, returning what it returned",
+                        "",
+                        "this raises the error of the task if it failed or was stopped",
+                    ],
+                },
+                anno_lua::Field {
+                    name: "interval",
+                    ty: "fun(dur: Duration, task: fun(): nil): integer",
+                    docs: &[
+                        "calls a function every `dur`, between frames",
+                        "",
+                        "this returns an id that you can use to stop the timer",
+                    ],
+                },
+                anno_lua::Field {
+                    name: "after",
+                    ty: "fun(dur: Duration, task: fun(): nil): integer",
+                    docs: &[
+                        "calls a function once after `dur`, between frames",
+                        "",
+                        "this returns an id that you can use to stop the timer",
+                    ],
+                },
                 anno_lua::Field {
                     name: "stop",
                     ty: "fun(integer): boolean",
-                    docs: &["attempts to stop a running task"],
+                    docs: &["attempts to stop a running task, a timer or a `Value` subscription"],
                 },
             ],
         })
@@ -134,69 +179,390 @@ impl mlua::UserData for Runtime {
         methods.add_function("spawn", |lua, value: mlua::Value| {
             use tokio_stream::StreamExt as _;
 
+            let (id, report) = lua.app_data_mut::<RunningTasks>().unwrap().reserve();
+
             let handle = match value {
                 mlua::Value::Function(function) => {
-                    let future = function.call_async::<()>(());
-                    tokio::spawn(async move {
-                        let _ = future.await;
-                    })
+                    let future = function.call_async::<mlua::MultiValue>(());
+                    tokio::spawn(async move { report.complete(future.await) })
                 }
 
                 mlua::Value::Thread(thread) => {
-                    let mut stream = thread.into_async::<()>(());
+                    let mut stream = thread.into_async::<mlua::MultiValue>(());
                     tokio::spawn(async move {
-                        while let Some(..) = stream.next().await {
-                            //
+                        let mut last = mlua::MultiValue::new();
+                        while let Some(next) = stream.next().await {
+                            match next {
+                                Ok(values) => last = values,
+                                Err(err) => return report.complete(Err(err)),
+                            }
                         }
+                        report.complete(Ok(last))
                     })
                 }
 
                 _ => {
+                    lua.app_data_mut::<RunningTasks>()
+                        .unwrap()
+                        .tasks
+                        .remove(&id);
                     return Err(mlua::Error::runtime(
                         "only functions or coroutines can be spawned",
                     ));
                 }
             };
 
-            let id = lua
-                .app_data_mut::<RunningTasks>()
+            lua.app_data_mut::<RunningTasks>()
                 .unwrap()
-                .push(handle.abort_handle());
+                .push(id, handle.abort_handle());
             Ok(id)
         });
 
+        methods.add_function("status", |lua, id: u64| {
+            let tasks = lua.app_data_ref::<RunningTasks>().unwrap();
+            let Some(task) = tasks.tasks.get(&id) else {
+                return Ok(None);
+            };
+            Ok(Some(task.status.borrow().name()))
+        });
+
+        methods.add_async_function("join", |lua, id: u64| async move {
+            let status = lua
+                .app_data_ref::<RunningTasks>()
+                .unwrap()
+                .tasks
+                .get(&id)
+                .map(|task| task.status.clone());
+
+            let Some(mut status) = status else {
+                return Err(mlua::Error::runtime(format!("unknown task: {id}")));
+            };
+
+            let status = match status.wait_for(|status| !status.is_running()).await {
+                Ok(status) => status.clone(),
+                Err(..) => TaskStatus::Stopped,
+            };
+
+            // the result has been taken, so the task is forgotten
+            lua.app_data_mut::<RunningTasks>()
+                .unwrap()
+                .tasks
+                .remove(&id);
+
+            match status {
+                TaskStatus::Finished(values) => Ok(values),
+                TaskStatus::Failed(err) => Err(err),
+                _ => Err(mlua::Error::runtime(format!("task {id} was stopped"))),
+            }
+        });
+
+        methods.add_function(
+            "interval",
+            |lua, (duration, task): (Duration, mlua::Function)| {
+                let period = duration.into();
+                let now = Clock::now(lua);
+                let mut tasks = lua.app_data_mut::<RunningTasks>().unwrap();
+                Ok(tasks.push_timer(now + period, Some(period), task))
+            },
+        );
+
+        methods.add_function(
+            "after",
+            |lua, (duration, task): (Duration, mlua::Function)| {
+                let now = Clock::now(lua);
+                let mut tasks = lua.app_data_mut::<RunningTasks>().unwrap();
+                Ok(tasks.push_timer(now + duration.into(), None, task))
+            },
+        );
+
         methods.add_function("stop", |lua, id: Option<u64>| {
             let Some(id) = id else { return Ok(false) };
-            let mut tasks = lua.app_data_mut::<RunningTasks>().unwrap();
-            Ok(tasks.shutdown(id))
+            let stopped = lua.app_data_mut::<RunningTasks>().unwrap().shutdown(id);
+            Ok(stopped || Changes::unsubscribe(lua, id))
+        });
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum TaskStatus {
+    Running,
+    Finished(mlua::MultiValue),
+    Failed(mlua::Error),
+    Stopped,
+}
+
+impl TaskStatus {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Finished(..) => "finished",
+            Self::Failed(..) => "failed",
+            Self::Stopped => "stopped",
+        }
+    }
+
+    pub const fn is_running(&self) -> bool {
+        matches!(self, Self::Running)
+    }
+}
+
+struct Task {
+    abort: Option<tokio::task::AbortHandle>,
+    sender: Arc<tokio::sync::watch::Sender<TaskStatus>>,
+    status: tokio::sync::watch::Receiver<TaskStatus>,
+}
+
+impl Task {
+    fn stop(&self) {
+        if let Some(abort) = &self.abort {
+            abort.abort();
+        }
+        self.sender.send_if_modified(|status| {
+            let running = status.is_running();
+            if running {
+                *status = TaskStatus::Stopped;
+            }
+            running
         });
     }
 }
 
-#[derive(Default)]
+pub(crate) struct Report {
+    id: u64,
+    sender: Arc<tokio::sync::watch::Sender<TaskStatus>>,
+    failures: mpsc::Sender<(u64, mlua::Error)>,
+}
+
+impl Report {
+    pub(crate) fn complete(self, result: mlua::Result<mlua::MultiValue>) {
+        let status = match result {
+            Ok(values) => TaskStatus::Finished(values),
+            Err(err) => {
+                let _ = self.failures.send((self.id, err.clone()));
+                TaskStatus::Failed(err)
+            }
+        };
+        self.sender.send_replace(status);
+    }
+}
+
+/// The time that timers are scheduled against
+///
+/// This is the system time, unless a manual clock was set with [`Clock::manual`],
+/// which only moves when it is advanced, e.g. by the elapsed time given to the headless harness
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) enum Clock {
+    #[default]
+    System,
+    Manual(Instant),
+}
+
+impl Clock {
+    pub(crate) fn manual(lua: &mlua::Lua) {
+        lua.set_app_data(Self::Manual(Instant::now()));
+    }
+
+    pub(crate) fn now(lua: &mlua::Lua) -> Instant {
+        match lua.app_data_ref::<Self>().map(|clock| *clock) {
+            Some(Self::Manual(now)) => now,
+            _ => Instant::now(),
+        }
+    }
+
+    pub(crate) fn advance(lua: &mlua::Lua, dt: std::time::Duration) {
+        if let Some(mut clock) = lua.app_data_mut::<Self>()
+            && let Self::Manual(now) = &mut *clock
+        {
+            *now += dt;
+        }
+    }
+}
+
+struct Timer {
+    next: Instant,
+    period: Option<std::time::Duration>,
+    task: mlua::Function,
+}
+
+struct Watch {
+    path: String,
+    events: mpsc::Receiver<()>,
+    failures: mpsc::Receiver<String>,
+    task: mlua::Function,
+    // the watch stops once this entry is removed
+    _handle: WatchHandle,
+}
+
 pub struct RunningTasks {
-    tasks: HashMap<u64, tokio::task::AbortHandle>,
+    tasks: HashMap<u64, Task>,
+    timers: HashMap<u64, Timer>,
+    watches: HashMap<u64, Watch>,
+    failures: (
+        mpsc::Sender<(u64, mlua::Error)>,
+        mpsc::Receiver<(u64, mlua::Error)>,
+    ),
+}
+
+impl Default for RunningTasks {
+    fn default() -> Self {
+        Self {
+            tasks: HashMap::new(),
+            timers: HashMap::new(),
+            watches: HashMap::new(),
+            failures: mpsc::channel(),
+        }
+    }
 }
 
 impl RunningTasks {
-    fn push(&mut self, handle: tokio::task::AbortHandle) -> u64 {
+    const WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(100);
+    // how many tasks that are no longer running are kept, for their status and result
+    const MAX_FINISHED: usize = 64;
+
+    pub(crate) fn next_id() -> u64 {
         static TASK_ID: AtomicU64 = AtomicU64::new(0);
-        let id = TASK_ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        self.tasks.insert(id, handle);
+        TASK_ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+    }
+
+    pub(crate) fn reserve(&mut self) -> (u64, Report) {
+        self.forget_finished();
+
+        let id = Self::next_id();
+        let (sender, status) = tokio::sync::watch::channel(TaskStatus::Running);
+        let sender = Arc::new(sender);
+
+        let task = Task {
+            abort: None,
+            sender: Arc::clone(&sender),
+            status,
+        };
+        self.tasks.insert(id, task);
+
+        let report = Report {
+            id,
+            sender,
+            failures: self.failures.0.clone(),
+        };
+        (id, report)
+    }
+
+    // ids only ever increase, so the lowest ids are the oldest tasks
+    fn forget_finished(&mut self) {
+        let mut finished = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| !task.status.borrow().is_running())
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        if finished.len() <= Self::MAX_FINISHED {
+            return;
+        }
+
+        finished.sort_unstable();
+        for id in &finished[..finished.len() - Self::MAX_FINISHED] {
+            self.tasks.remove(id);
+        }
+    }
+
+    pub(crate) fn push(&mut self, id: u64, handle: tokio::task::AbortHandle) {
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.abort = Some(handle);
+        }
+    }
+
+    fn push_timer(
+        &mut self,
+        next: Instant,
+        period: Option<std::time::Duration>,
+        task: mlua::Function,
+    ) -> u64 {
+        let id = Self::next_id();
+        let timer = Timer { next, period, task };
+        self.timers.insert(id, timer);
+        id
+    }
+
+    pub(crate) fn push_watch(&mut self, path: String, task: mlua::Function) -> u64 {
+        let id = Self::next_id();
+        let (tx, events) = mpsc::channel();
+        let (failed, failures) = mpsc::channel();
+        let watch = Watch {
+            _handle: watch_for_changes(tx, failed, Watched::new(&path), Self::WATCH_DEBOUNCE),
+            path,
+            events,
+            failures,
+            task,
+        };
+        self.watches.insert(id, watch);
         id
     }
 
     fn shutdown(&mut self, task: u64) -> bool {
-        if let Some(task) = self.tasks.remove(&task) {
-            task.abort();
+        // stopped tasks are kept around so their status can still be queried,
+        // until they're joined or forgotten
+        if let Some(task) = self.tasks.get(&task) {
+            task.stop();
             return true;
         }
-        false
+        self.timers.remove(&task).is_some() || self.watches.remove(&task).is_some()
     }
 
     pub fn stop_all(&mut self) {
         for (_, task) in self.tasks.drain() {
-            task.abort();
+            task.stop();
+        }
+        self.timers.clear();
+        self.watches.clear();
+    }
+
+    /// Takes the errors of tasks that failed since the last call
+    pub fn take_failures(lua: &mlua::Lua) -> Vec<(u64, mlua::Error)> {
+        let tasks = lua.app_data_ref::<Self>().unwrap();
+        tasks.failures.1.try_iter().collect()
+    }
+
+    // these are called without the tasks borrowed, so they can start or stop other tasks
+    #[profiling::function]
+    pub fn run_callbacks(lua: &mlua::Lua) -> Vec<mlua::Error> {
+        let now = Clock::now(lua);
+
+        let mut due = vec![];
+        let mut changed = vec![];
+        let mut failed = vec![];
+
+        {
+            let mut this = lua.app_data_mut::<Self>().unwrap();
+            this.timers.retain(|_, timer| {
+                if timer.next > now {
+                    return true;
+                }
+                due.push(timer.task.clone());
+                match timer.period {
+                    Some(period) => {
+                        timer.next = now + period;
+                        true
+                    }
+                    None => false,
+                }
+            });
+
+            for watch in this.watches.values() {
+                if watch.events.try_iter().count() > 0 {
+                    changed.push((watch.task.clone(), watch.path.clone()));
+                }
+                failed.extend(watch.failures.try_iter().map(mlua::Error::runtime));
+            }
         }
+
+        let due = due
+            .into_iter()
+            .map(|task| Budget::guard(lua, || task.call::<()>(())));
+        let changed = changed
+            .into_iter()
+            .map(|(task, path)| Budget::guard(lua, || task.call::<()>(path)));
+        due.chain(changed)
+            .filter_map(Result::err)
+            .chain(failed)
+            .collect()
     }
 }
@@ -0,0 +1,126 @@
+use mlua::{LuaOptions, StdLib};
+
+use crate::Proxy;
+
+/// A policy for running untrusted scripts
+///
+/// By default only the `table`, `string`, `math`, `utf8` and `coroutine` libraries are loaded,
+/// every registered proxy except `Process` and `Fs` is available and there is no memory limit.
+#[derive(Clone, Debug)]
+pub struct Sandbox {
+    libs: StdLib,
+    proxies: Option<Vec<&'static str>>,
+    privileged: Vec<&'static str>,
+    require_path: Option<String>,
+    memory_limit: Option<usize>,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sandbox {
+    // these are removed from `os`, if it is allowed
+    const OS_DENIED: &[&str] = &[
+        "execute",
+        "exit",
+        "getenv",
+        "remove",
+        "rename",
+        "setlocale",
+        "tmpname",
+    ];
+
+    // these reach outside of the process, so they have to be allowed by name
+    const PRIVILEGED: &[&str] = &["Process", "Fs"];
+
+    pub fn new() -> Self {
+        Self {
+            libs: StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8 | StdLib::COROUTINE,
+            proxies: None,
+            privileged: Vec::new(),
+            require_path: None,
+            memory_limit: None,
+        }
+    }
+
+    /// Allows additional standard libraries
+    ///
+    /// `os` is loaded without the functions that touch the system or the process.
+    /// `io` and `debug` cannot be loaded in a sandbox.
+    pub fn allow_std(mut self, libs: StdLib) -> Self {
+        self.libs |= libs;
+        self
+    }
+
+    /// Only registers the proxies with these names, e.g. `Runtime` or `Value`
+    ///
+    /// `Process` and `Fs` are only registered when they are named here.
+    pub fn allow_proxies(mut self, names: impl IntoIterator<Item = &'static str>) -> Self {
+        for name in names {
+            if Self::PRIVILEGED.contains(&name) {
+                self.privileged.push(name);
+            } else {
+                self.proxies.get_or_insert_with(Vec::new).push(name);
+            }
+        }
+        self
+    }
+
+    /// Allows `require`, only searching this `package.path`
+    pub fn require_path(mut self, path: impl ToString) -> Self {
+        self.require_path = Some(path.to_string());
+        self.libs |= StdLib::PACKAGE;
+        self
+    }
+
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    pub(crate) fn allows(&self, proxy: &Proxy) -> bool {
+        if Self::PRIVILEGED.contains(&proxy.name) {
+            return self.privileged.contains(&proxy.name);
+        }
+        self.proxies
+            .as_ref()
+            .is_none_or(|names| names.contains(&proxy.name))
+    }
+
+    pub(crate) fn create_lua(&self) -> mlua::Result<mlua::Lua> {
+        // `io.open`, `io.lines` and `io.popen` can all reach the file system or a shell
+        if self.libs & StdLib::IO == StdLib::IO {
+            return Err(mlua::Error::runtime("io cannot be loaded in a sandbox"));
+        }
+
+        let lua = mlua::Lua::new_with(self.libs, LuaOptions::default())?;
+
+        if let Some(limit) = self.memory_limit {
+            lua.set_memory_limit(limit)?;
+        }
+
+        let globals = lua.globals();
+
+        if let Ok(os) = globals.get::<mlua::Table>("os") {
+            for name in Self::OS_DENIED {
+                os.set(*name, mlua::Value::Nil)?;
+            }
+        }
+
+        if let Ok(package) = globals.get::<mlua::Table>("package") {
+            package.set("path", self.require_path.as_deref().unwrap_or_default())?;
+            // native modules would escape the sandbox entirely
+            package.set("cpath", "")?;
+        }
+
+        // these can load arbitrary files or bytecode
+        for name in ["dofile", "loadfile"] {
+            globals.set(name, mlua::Value::Nil)?;
+        }
+
+        Ok(lua)
+    }
+}
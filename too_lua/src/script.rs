@@ -1,28 +1,88 @@
-use std::{
-    path::{Path, PathBuf},
-    time::{Duration, SystemTime},
+use std::{path::PathBuf, time::Duration};
+
+use mlua::{AnyUserData, FromLua, IntoLuaMulti};
+use too::math::Vec2;
+
+use crate::{
+    FileLoader, Loader, MemoryLoader, Tree, UiBuilder,
+    budget::Budget,
+    errors::Reported,
+    reactive::Changes,
+    runtime::RunningTasks,
+    watcher::{WatchHandle, Watched, watch_for_changes},
 };
 
-use mlua::AnyUserData;
+/// What a script returns, either a `view` function or a table of hooks
+///
+/// When a script is reloaded, the old script's `save` is called and what it returns is
+/// given to the new script's `on_reload`, along with the old script's `version`.
+/// If `save` fails, the error is reported and `on_reload` is given `nil` instead
+struct Hooks {
+    version: Option<mlua::Integer>,
+    view: mlua::Function,
+    save: Option<mlua::Function>,
+    init: Option<mlua::Function>,
+    on_frame: Option<mlua::Function>,
+    on_resize: Option<mlua::Function>,
+    on_reload: Option<mlua::Function>,
+    on_exit: Option<mlua::Function>,
+}
 
-use crate::{Tree, UiBuilder, runtime::RunningTasks};
+impl FromLua for Hooks {
+    fn from_lua(value: mlua::Value, _lua: &mlua::Lua) -> mlua::Result<Self> {
+        match value {
+            mlua::Value::Function(view) => Ok(Self {
+                version: None,
+                view,
+                save: None,
+                init: None,
+                on_frame: None,
+                on_resize: None,
+                on_reload: None,
+                on_exit: None,
+            }),
+            mlua::Value::Table(table) => Ok(Self {
+                view: table
+                    .get::<Option<mlua::Function>>("view")?
+                    .ok_or_else(|| mlua::Error::runtime("script must return a `view` function"))?,
+                version: table.get("version")?,
+                save: table.get("save")?,
+                init: table.get("init")?,
+                on_frame: table.get("on_frame")?,
+                on_resize: table.get("on_resize")?,
+                on_reload: table.get("on_reload")?,
+                on_exit: table.get("on_exit")?,
+            }),
+            value => Err(mlua::Error::runtime(format!(
+                "script must return a function or a table, got: {}",
+                value.type_name()
+            ))),
+        }
+    }
+}
 
 pub struct Script {
-    path: PathBuf,
-    update: mlua::Function,
+    loader: Box<dyn Loader>,
+    // this is `None` until the script has loaded successfully
+    hooks: Option<Hooks>,
     events: std::sync::mpsc::Receiver<()>,
-    _handle: Option<std::thread::JoinHandle<()>>,
+    failures: std::sync::mpsc::Receiver<String>,
+    _handle: Option<WatchHandle>,
 }
 
 impl Script {
     pub fn from_source(source: &str, lua: &mlua::Lua) -> mlua::Result<Self> {
         let (_tx, events) = std::sync::mpsc::channel();
-        Ok(Self {
-            update: lua.load(source).eval()?,
+        let (_tx, failures) = std::sync::mpsc::channel();
+        let mut this = Self {
+            loader: Box::new(MemoryLoader::new("source", source)),
+            hooks: None,
             events,
+            failures,
             _handle: None,
-            path: PathBuf::from("<null>"),
-        })
+        };
+        this.hooks = Some(lua.load(source).set_name(this.chunk_name()).eval()?);
+        Ok(this)
     }
 
     pub fn new(
@@ -30,92 +90,215 @@ impl Script {
         timeout: Option<Duration>,
         lua: &mlua::Lua,
     ) -> mlua::Result<Self> {
-        let path: PathBuf = path.into();
-        let data = std::fs::read_to_string(&path)?;
+        Self::from_loader(FileLoader::new(path), timeout, lua)
+    }
 
-        let update: mlua::Function = lua.load(data).eval()?;
-        lua.set_app_data(Tree::new(lua)?);
-        let data = lua.globals().get::<AnyUserData>("__USER_STATE").ok();
-        update.call::<()>((UiBuilder, data))?;
+    pub fn from_loader(
+        loader: impl Loader,
+        timeout: Option<Duration>,
+        lua: &mlua::Lua,
+    ) -> mlua::Result<Self> {
+        let mut this = Self::unloaded(loader, timeout, lua);
+        this.reload(lua)?;
+        Ok(this)
+    }
+
+    /// Watches a script without loading it
+    ///
+    /// Until [`Script::reload`] succeeds, this has an empty tree
+    ///
+    /// Modules loaded with `require` are watched along with the script
+    pub fn unloaded(loader: impl Loader, timeout: Option<Duration>, lua: &mlua::Lua) -> Self {
+        let watched = loader.path().map(Watched::new).unwrap_or_default();
+        lua.set_app_data(watched.clone());
 
         let (tx, events) = std::sync::mpsc::channel();
-        Ok(Self {
-            update,
+        let (failed, failures) = std::sync::mpsc::channel();
+        Self {
+            loader: Box::new(loader),
+            hooks: None,
             events,
-            _handle: timeout.map(|timeout| Self::watch_for_changes(tx, path.clone(), timeout)),
-            path,
-        })
+            failures,
+            _handle: timeout.map(|timeout| watch_for_changes(tx, failed, watched, timeout)),
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.hooks.is_some()
     }
 
     #[profiling::function]
     pub fn update(&self, lua: &mlua::Lua) -> mlua::Result<()> {
-        lua.set_app_data(Tree::new(lua)?);
+        let Some(hooks) = &self.hooks else {
+            return Ok(());
+        };
+
+        let previous = lua.set_app_data(Tree::new(lua)?);
         let data = lua.globals().get::<AnyUserData>("__USER_STATE").ok();
-        self.update.call::<()>((UiBuilder, data))
+        let result = Budget::guard(lua, || hooks.view.call::<()>((UiBuilder, data)));
+
+        // keep showing the previous ui rather than a partially built one
+        if let (Err(..), Some(previous)) = (&result, previous) {
+            lua.set_app_data(previous);
+        }
+        result
     }
 
+    /// Loads and evaluates a new script
+    ///
+    /// The new tree is built off to the side and only replaces the current one if
+    /// both steps succeed, otherwise the previous script keeps running as it was
     #[profiling::function]
     pub fn reload_source(&mut self, source: &str, lua: &mlua::Lua) -> mlua::Result<()> {
-        Self::reset_loaded(lua);
-        lua.app_data_mut::<RunningTasks>().unwrap().stop_all();
-        self.update = lua.load(source).eval()?;
+        // the tree is built before any state is swapped, so failing here has nothing to undo
+        let tree = Tree::new(lua)?;
+        let loaded = Self::reset_loaded(lua);
+
+        // tasks and subscriptions from the new script shouldn't outlive a failed build,
+        // and those from the old script shouldn't outlive a successful one.
+        // this happens before the script is evaluated, which can already subscribe or spawn
+        let previous = lua.set_app_data(RunningTasks::default());
+        let subscriptions = Changes::take_subscriptions(lua);
+        let tree = lua.set_app_data(tree);
+
+        let data = lua.globals().get::<AnyUserData>("__USER_STATE").ok();
+        let result = Budget::guard(lua, || {
+            let hooks = lua
+                .load(source)
+                .set_name(self.chunk_name())
+                .eval::<Hooks>()?;
+
+            if let Some(previous) = &self.hooks {
+                // a broken `save` loses the state, but it mustn't keep the old script around,
+                // otherwise the script could never be reloaded with a fixed `save`
+                let state = match &previous.save {
+                    Some(save) => save.call::<mlua::Value>(()).unwrap_or_else(|err| {
+                        Reported::report(lua, "save", err);
+                        mlua::Value::Nil
+                    }),
+                    None => mlua::Value::Nil,
+                };
+                if let Some(on_reload) = &hooks.on_reload {
+                    on_reload.call::<()>((state, previous.version))?;
+                }
+            } else if let Some(init) = &hooks.init {
+                // `init` only runs once, the first time the script loads
+                init.call::<()>(data.clone())?;
+            }
+            hooks.view.call::<()>((UiBuilder, data))?;
+            Ok(hooks)
+        });
+
+        let hooks = match result {
+            Ok(hooks) => hooks,
+            Err(err) => {
+                if let Some(tree) = tree {
+                    lua.set_app_data(tree);
+                }
+                if let Some(previous) = previous {
+                    if let Some(mut tasks) = lua.set_app_data(previous) {
+                        tasks.stop_all();
+                    }
+                }
+                Changes::restore_subscriptions(lua, subscriptions);
+                Self::restore_loaded(lua, loaded);
+                return Err(err);
+            }
+        };
+
+        if let Some(mut previous) = previous {
+            previous.stop_all();
+        }
+        self.hooks = Some(hooks);
         Ok(())
     }
 
+    /// Reloads the script from its loader, see [`Script::reload_source`]
     pub fn reload(&mut self, lua: &mlua::Lua) -> mlua::Result<()> {
-        let data = std::fs::read_to_string(&self.path)?;
+        let data = self.loader.load()?;
         self.reload_source(&data, lua)
     }
 
+    /// Calls `on_frame` with the time since the last frame, in seconds
+    pub fn frame(&self, lua: &mlua::Lua, dt: f32) -> mlua::Result<()> {
+        self.call_hook(lua, |hooks| hooks.on_frame.as_ref(), dt)
+    }
+
+    /// Calls `on_resize` with the new size
+    pub fn resize(&self, lua: &mlua::Lua, size: Vec2) -> mlua::Result<()> {
+        self.call_hook(lua, |hooks| hooks.on_resize.as_ref(), (size.x, size.y))
+    }
+
+    /// Calls `on_exit`, this should be called before the application quits
+    pub fn exit(&self, lua: &mlua::Lua) -> mlua::Result<()> {
+        self.call_hook(lua, |hooks| hooks.on_exit.as_ref(), ())
+    }
+
+    fn call_hook(
+        &self,
+        lua: &mlua::Lua,
+        hook: impl FnOnce(&Hooks) -> Option<&mlua::Function>,
+        args: impl IntoLuaMulti,
+    ) -> mlua::Result<()> {
+        let Some(hook) = self.hooks.as_ref().and_then(hook) else {
+            return Ok(());
+        };
+        Budget::guard(lua, || hook.call::<()>(args))
+    }
+
     pub fn should_reload(&self) -> bool {
         self.events.try_recv().is_ok()
     }
 
-    fn reset_loaded(lua: &mlua::Lua) {
+    /// Takes the errors from watching the script and its modules for changes
+    pub fn watch_errors(&self) -> Vec<mlua::Error> {
+        self.failures.try_iter().map(mlua::Error::runtime).collect()
+    }
+
+    // errors are reported relative to this, so they can be traced back to the source
+    fn chunk_name(&self) -> String {
+        match self.loader.path() {
+            Some(path) => format!("@{}", path.display()),
+            None => format!("={}", self.loader.name()),
+        }
+    }
+
+    // this returns the previously loaded modules so a failed reload can put them back
+    fn reset_loaded(lua: &mlua::Lua) -> Vec<(mlua::String, mlua::Value)> {
         let Ok(modules) = lua.globals().get::<mlua::Table>("__TOO_LOADED") else {
-            return;
+            return vec![];
         };
 
         let Ok(package) = lua.globals().get::<mlua::Table>("package") else {
-            return;
+            return vec![];
         };
 
         let Ok(loaded) = package.get::<mlua::Table>("loaded") else {
-            return;
+            return vec![];
         };
 
+        let mut previous = vec![];
         for (k, _) in modules.pairs::<mlua::String, mlua::Value>().flatten() {
+            if let Ok(module) = loaded.get::<mlua::Value>(&k) {
+                previous.push((k.clone(), module));
+            }
             let _ = modules.set(&k, "false");
             let _ = loaded.set(k, false);
         }
+        previous
     }
 
-    fn watch_for_changes(
-        tx: std::sync::mpsc::Sender<()>,
-        path: PathBuf,
-        timeout: Duration,
-    ) -> std::thread::JoinHandle<()> {
-        // FIXME use notify here
-        fn last_modified(path: &Path) -> Option<SystemTime> {
-            let md = std::fs::metadata(path).ok()?;
-            md.is_file().then_some(md.modified().ok()?)
-        }
+    fn restore_loaded(lua: &mlua::Lua, previous: Vec<(mlua::String, mlua::Value)>) {
+        let Ok(package) = lua.globals().get::<mlua::Table>("package") else {
+            return;
+        };
 
-        std::thread::spawn(move || {
-            let mut last = SystemTime::now();
-            loop {
-                if let Some((elapsed, next)) = last_modified(&path)
-                    .and_then(|md| md.duration_since(last).ok().map(|e| (e, md)))
-                {
-                    if elapsed >= Duration::from_millis(100) {
-                        last = next;
-                        if tx.send(()).is_err() {
-                            return;
-                        }
-                    }
-                }
-                std::thread::sleep(timeout);
-            }
-        })
+        let Ok(loaded) = package.get::<mlua::Table>("loaded") else {
+            return;
+        };
+
+        for (k, module) in previous {
+            let _ = loaded.set(k, module);
+        }
     }
 }
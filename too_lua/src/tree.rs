@@ -1,11 +1,23 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use mlua::{FromLua, UserData};
 use too::helpers::hash_fnv_1a;
 
+use crate::reactive::Changes;
+
 #[derive(Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub struct LuaId(usize);
 
+impl LuaId {
+    pub(crate) const fn new(index: usize) -> Self {
+        Self(index)
+    }
+
+    pub(crate) const fn index(self) -> usize {
+        self.0
+    }
+}
+
 impl std::fmt::Debug for LuaId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.0.fmt(f)
@@ -15,7 +27,6 @@ impl std::fmt::Debug for LuaId {
 #[derive(Debug)]
 pub struct Node {
     pub(crate) name: u64,
-    #[allow(dead_code)]
     pub(crate) parent: Option<LuaId>,
     pub(crate) children: Vec<LuaId>,
     pub(crate) data: mlua::Value,
@@ -45,13 +56,23 @@ impl std::ops::IndexMut<LuaId> for Vec<Node> {
     }
 }
 
+/// A node whose children are built by a function, and rebuilt when a value it read changes
+#[derive(Debug)]
+pub(crate) struct Reactive {
+    builder: mlua::Function,
+    reads: HashSet<usize>,
+}
+
 #[derive(Debug)]
 pub struct Tree {
     pub(crate) root: LuaId,
     pub(crate) map: Vec<Node>,
     pub(crate) names: HashMap<LuaId, mlua::String>,
     pub(crate) lazies: HashMap<LuaId, mlua::Function>,
+    pub(crate) reactives: HashMap<LuaId, Reactive>,
 
+    // ids of detached nodes, which are reused for new nodes
+    free: Vec<LuaId>,
     stack: Vec<LuaId>,
     proxy: mlua::Table,
 }
@@ -83,7 +104,9 @@ impl Tree {
             map,
             names,
             lazies: HashMap::new(),
+            reactives: HashMap::new(),
 
+            free: Vec::new(),
             stack: vec![root],
             proxy: table,
         })
@@ -192,17 +215,127 @@ impl Tree {
         self.lazies.insert(current, function);
     }
 
-    fn proxy(&mut self, name: mlua::String) -> mlua::Result<mlua::Value> {
+    pub(crate) fn add_reactive(lua: &mlua::Lua, builder: mlua::Function) -> mlua::Result<()> {
+        let id = {
+            let mut tree = lua.app_data_mut::<Self>().expect("tree");
+            let name = lua.create_string("reactive")?;
+            tree.push_node(name)
+        };
+        Self::build_reactive(lua, id, builder)
+    }
+
+    /// Rebuilds the reactive nodes that read a value that has changed since the last call
+    #[profiling::function]
+    /// Rebuilds every reactive node that read a changed value
+    ///
+    /// A failing builder doesn't stop the others, the errors are returned together
+    pub(crate) fn rebuild_reactives(lua: &mlua::Lua) -> Vec<mlua::Error> {
+        let dirty = Changes::take_dirty(lua);
+        if dirty.is_empty() {
+            return Vec::new();
+        }
+
+        let stale = {
+            let tree = lua.app_data_ref::<Self>().expect("tree");
+            let mut stale = tree
+                .reactives
+                .iter()
+                .filter(|(_, reactive)| !reactive.reads.is_disjoint(&dirty))
+                .map(|(&id, reactive)| (id, reactive.builder.clone()))
+                .collect::<Vec<_>>();
+
+            // rebuilding parents first skips their stale children
+            stale.sort_by_cached_key(|&(id, _)| tree.depth(id));
+            stale
+        };
+
+        let mut errors = Vec::new();
+        for (id, builder) in stale {
+            // a detached node's id may have been reused by a node with a different builder
+            let detached = !lua
+                .app_data_ref::<Self>()
+                .expect("tree")
+                .reactives
+                .get(&id)
+                .is_some_and(|reactive| reactive.builder == builder);
+            if detached {
+                continue;
+            }
+            if let Err(err) = Self::build_reactive(lua, id, builder) {
+                errors.push(err);
+            }
+        }
+        errors
+    }
+
+    fn depth(&self, mut id: LuaId) -> usize {
+        let mut depth = 0;
+        while let Some(parent) = self.map[id].parent {
+            depth += 1;
+            id = parent;
+        }
+        depth
+    }
+
+    fn build_reactive(lua: &mlua::Lua, id: LuaId, builder: mlua::Function) -> mlua::Result<()> {
+        let depth = {
+            let mut tree = lua.app_data_mut::<Self>().expect("tree");
+            tree.detach_children(id);
+            tree.stack.push(id);
+            tree.stack.len() - 1
+        };
+
+        let (result, reads) = Changes::track(lua, || builder.call::<()>(UiBuilder));
+
+        let mut tree = lua.app_data_mut::<Self>().expect("tree");
+        // a failed builder can leave its partially built nodes on the stack
+        tree.stack.truncate(depth);
+        tree.reactives.insert(id, Reactive { builder, reads });
+        result
+    }
+
+    // the detached nodes are cleared out and their ids are reused by `push_node`,
+    // so rebuilding a reactive node every frame doesn't grow the tree
+    fn detach_children(&mut self, id: LuaId) {
+        let mut queue = std::mem::take(&mut self.map[id].children);
+        while let Some(child) = queue.pop() {
+            self.lazies.remove(&child);
+            self.reactives.remove(&child);
+            self.names.remove(&child);
+
+            let node = &mut self.map[child];
+            queue.append(&mut node.children);
+            node.parent = None;
+            node.data = mlua::Value::Nil;
+            self.free.push(child);
+        }
+    }
+
+    fn push_node(&mut self, name: mlua::String) -> LuaId {
         let pid = self.stack.last().copied();
 
-        let id = Self::current_id(self);
-        self.map.push(Node::new(name.clone(), pid));
+        let node = Node::new(name.clone(), pid);
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.map[id] = node;
+                id
+            }
+            None => {
+                let id = Self::current_id(self);
+                self.map.push(node);
+                id
+            }
+        };
         self.names.insert(id, name);
 
         if let Some(parent) = pid {
             self.map[parent].children.push(id);
         }
+        id
+    }
 
+    fn proxy(&mut self, name: mlua::String) -> mlua::Result<mlua::Value> {
+        let id = self.push_node(name);
         self.stack.push(id);
         Ok(mlua::Value::Table(self.proxy.clone()))
     }
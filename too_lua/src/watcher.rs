@@ -0,0 +1,173 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{RecvTimeoutError, Sender},
+    },
+    time::{Duration, Instant},
+};
+
+use notify::{EventKind, RecursiveMode};
+
+/// A set of files and directories that can grow while they are being watched
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Watched {
+    paths: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl Watched {
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+        let this = Self::default();
+        this.insert(path);
+        this
+    }
+
+    pub(crate) fn insert(&self, path: impl Into<PathBuf>) {
+        self.paths.lock().unwrap().insert(path.into());
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        self.paths.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+// how often newly added paths are picked up
+const RESCAN: Duration = Duration::from_millis(250);
+
+struct Target {
+    path: PathBuf,
+    is_dir: bool,
+}
+
+impl Target {
+    // this is `None` if the path doesn't exist (yet)
+    fn subscribe(watcher: &mut impl notify::Watcher, path: &Path) -> notify::Result<Option<Self>> {
+        let Ok(path) = path.canonicalize() else {
+            return Ok(None);
+        };
+        if path.is_dir() {
+            watcher.watch(&path, RecursiveMode::Recursive)?;
+            return Ok(Some(Self { path, is_dir: true }));
+        }
+
+        // editors often save by renaming over the file, which would end a watch on the file itself
+        let Some(parent) = path.parent() else {
+            return Ok(None);
+        };
+        watcher.watch(parent, RecursiveMode::NonRecursive)?;
+        Ok(Some(Self {
+            path,
+            is_dir: false,
+        }))
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        if self.is_dir {
+            path.starts_with(&self.path)
+        } else {
+            path == self.path
+        }
+    }
+}
+
+fn is_change(targets: &HashMap<PathBuf, Target>, event: &notify::Result<notify::Event>) -> bool {
+    let Ok(event) = event else { return false };
+    if matches!(event.kind, EventKind::Access(..)) {
+        return false;
+    }
+    event
+        .paths
+        .iter()
+        .any(|path| targets.values().any(|target| target.matches(path)))
+}
+
+/// A running watch, which is stopped when this is dropped
+pub(crate) struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    _handle: std::thread::JoinHandle<()>,
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        // the thread notices this within a rescan, and drops its watcher on the way out
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Sends an event when any of the watched paths change
+///
+/// Changes are coalesced until nothing has changed for `debounce`.
+/// This runs on its own thread, so errors are sent to `failures` rather than printed
+pub(crate) fn watch_for_changes(
+    tx: Sender<()>,
+    failures: Sender<String>,
+    watched: Watched,
+    debounce: Duration,
+) -> WatchHandle {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = Arc::clone(&stop);
+
+    let handle = std::thread::spawn(move || {
+        let (events_tx, events) = std::sync::mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(events_tx) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                _ = failures.send(format!("cannot watch for changes: {err}"));
+                return;
+            }
+        };
+
+        let mut targets = HashMap::<PathBuf, Target>::new();
+        // these are reported once, rather than on every scan
+        let mut failed = HashSet::<PathBuf>::new();
+        while !stopped.load(Ordering::Relaxed) {
+            for path in watched.paths() {
+                if targets.contains_key(&path) || failed.contains(&path) {
+                    continue;
+                }
+                // the path may not exist yet, so this is retried on the next scan
+                match Target::subscribe(&mut watcher, &path) {
+                    Ok(Some(target)) => _ = targets.insert(path, target),
+                    Ok(None) => {}
+                    Err(err) => {
+                        _ = failures.send(format!("cannot watch {}: {err}", path.display()));
+                        failed.insert(path);
+                    }
+                }
+            }
+
+            let event: notify::Result<notify::Event> = match events.recv_timeout(RESCAN) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+
+            if !is_change(&targets, &event) {
+                continue;
+            }
+
+            // editors tend to write a burst of events for a single save,
+            // but changes to other files in the same directory shouldn't hold it back
+            let mut quiet = Instant::now() + debounce;
+            loop {
+                match events.recv_timeout(quiet.saturating_duration_since(Instant::now())) {
+                    Ok(event) if is_change(&targets, &event) => quiet = Instant::now() + debounce,
+                    Ok(..) => {}
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            if stopped.load(Ordering::Relaxed) || tx.send(()).is_err() {
+                return;
+            }
+        }
+    });
+
+    WatchHandle {
+        stop,
+        _handle: handle,
+    }
+}
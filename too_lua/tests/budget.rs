@@ -0,0 +1,46 @@
+mod common;
+
+use std::time::Duration;
+
+use common::SharedLoader;
+use too_lua::Headless;
+
+#[test]
+fn infinite_loops_abort_and_keep_the_previous_tree() {
+    let loader = SharedLoader::new(
+        r#"
+        local spin = Value.persist("spin", false)
+        return {
+            on_frame = function()
+                while spin.value do end
+            end,
+            view = function(ui)
+                ui.label "first"
+            end,
+        }
+        "#,
+    );
+    let mut harness = Headless::from_loader(loader.clone())
+        .budget(Duration::from_millis(50))
+        .start()
+        .unwrap();
+    assert!(harness.render().unwrap().text().contains("first"));
+
+    // a hook that never returns is aborted, and the next frame still renders
+    assert!(harness.set_value("spin", true).unwrap());
+    assert!(harness.render().unwrap().text().contains("first"));
+    let errors = harness.take_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].to_string().contains("exceeded its budget"));
+
+    // as does a new script that never finishes building, or never finishes loading
+    for source in [
+        "return function(ui) ui.label 'second' while true do end end",
+        "while true do end return function(ui) end",
+    ] {
+        loader.set(source);
+        let err = harness.reload().unwrap_err();
+        assert!(err.to_string().contains("exceeded its budget"));
+        assert!(harness.render().unwrap().text().contains("first"));
+    }
+}
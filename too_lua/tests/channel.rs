@@ -0,0 +1,57 @@
+use too_lua::{Channel, Headless, bindings::Value};
+
+#[test]
+fn channels_carry_values_both_ways() {
+    let events = Channel::new("events");
+    let queued = Channel::new("queued");
+
+    let mut harness = Headless::from_source(
+        r#"
+        local events = Channel.open("events")
+        local queued = Channel.open("queued")
+        local got = Value.persist("got", 0)
+        local waited = Value.persist("waited", 0)
+        assert(not pcall(Channel.open, "missing"))
+
+        return {
+            init = function()
+                Runtime.spawn(function()
+                    while true do
+                        waited.value = waited.value + queued:recv()
+                    end
+                end)
+            end,
+            on_frame = function()
+                local value = events:try_recv()
+                if value then
+                    got.value = value
+                    events:send(value * 2)
+                end
+            end,
+            view = function(ui)
+                ui.label(events:name())
+            end,
+        }
+        "#,
+    )
+    .channel(events.clone())
+    .channel(queued.clone())
+    .start()
+    .unwrap();
+
+    harness.render().unwrap();
+    assert_eq!(events.try_recv(), None);
+
+    assert!(events.send(21_isize));
+    harness.render().unwrap();
+    assert_eq!(harness.value("got"), Some(Value::Signed(21)));
+    assert_eq!(events.try_recv(), Some(Value::Signed(42)));
+
+    // a task waiting on `recv` wakes up for each value
+    assert!(queued.send(1_isize));
+    assert!(queued.send(2_isize));
+    harness.render().unwrap();
+    harness.render().unwrap();
+    assert_eq!(harness.value("waited"), Some(Value::Signed(3)));
+    assert!(harness.take_errors().is_empty());
+}
@@ -0,0 +1,31 @@
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+
+use too_lua::Loader;
+
+/// A loader whose source can be changed between reloads
+#[derive(Clone, Default)]
+pub struct SharedLoader(Arc<Mutex<String>>);
+
+impl SharedLoader {
+    pub fn new(source: &str) -> Self {
+        let this = Self::default();
+        this.set(source);
+        this
+    }
+
+    pub fn set(&self, source: &str) {
+        *self.0.lock().unwrap() = source.to_string();
+    }
+}
+
+impl Loader for SharedLoader {
+    fn name(&self) -> String {
+        String::from("shared")
+    }
+
+    fn load(&self) -> std::io::Result<String> {
+        Ok(self.0.lock().unwrap().clone())
+    }
+}
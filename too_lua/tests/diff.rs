@@ -0,0 +1,79 @@
+use serde_json::json;
+use too_lua::{NodeDump, TreeChange, TreeDiff, TreeDump};
+
+fn node(id: usize, name: &str, parent: usize, children: &[usize]) -> NodeDump {
+    NodeDump {
+        id,
+        name: name.to_string(),
+        parent: Some(parent),
+        children: children.to_vec(),
+        params: serde_json::Value::Null,
+    }
+}
+
+fn label(id: usize, parent: usize, text: &str) -> NodeDump {
+    NodeDump {
+        params: json!({ "text": text }),
+        ..node(id, "label", parent, &[])
+    }
+}
+
+fn dump(mut nodes: Vec<NodeDump>, children: &[usize]) -> TreeDump {
+    nodes.push(NodeDump {
+        parent: None,
+        ..node(0, "root", 0, children)
+    });
+    nodes.sort_by_key(|node| node.id);
+    TreeDump { root: 0, nodes }
+}
+
+#[test]
+fn diff_adds_removes_moves_and_changes() {
+    let old = dump(
+        vec![
+            node(1, "vertical", 0, &[2, 3, 4, 8]),
+            label(2, 1, "a"),
+            label(3, 1, "b"),
+            node(4, "button", 1, &[]),
+            node(8, "separator", 1, &[]),
+            node(5, "horizontal", 0, &[]),
+        ],
+        &[1, 5],
+    );
+    let new = dump(
+        vec![
+            node(1, "vertical", 0, &[2, 3, 7]),
+            label(2, 1, "A"),
+            label(3, 1, "b"),
+            label(7, 1, "c"),
+            node(5, "horizontal", 0, &[6]),
+            node(6, "button", 5, &[]),
+        ],
+        &[1, 5],
+    );
+
+    let diff = TreeDiff::new(&old, &new);
+    assert_eq!(
+        diff.changes,
+        [
+            TreeChange::Moved {
+                from: "vertical[0]/button[0]".into(),
+                to: "horizontal[0]/button[0]".into(),
+            },
+            TreeChange::Removed {
+                path: "vertical[0]/separator[0]".into(),
+            },
+            TreeChange::Changed {
+                path: "vertical[0]/label[0]".into(),
+                old: json!({ "text": "a" }),
+                new: json!({ "text": "A" }),
+            },
+            TreeChange::Added {
+                path: "vertical[0]/label[2]".into(),
+            },
+        ]
+    );
+    assert_eq!(diff.summary(), "1 added, 1 removed, 1 moved, 1 changed");
+
+    assert!(TreeDiff::new(&new, &new).is_empty());
+}
@@ -0,0 +1,33 @@
+use too_lua::{Headless, TreeDump};
+
+#[test]
+fn dump_round_trips_through_json() {
+    let mut harness = Headless::from_source(
+        r#"
+        return function(ui)
+            ui.vertical {
+                ui.label "hello",
+                ui.label "world",
+            }
+        end
+        "#,
+    )
+    .start()
+    .unwrap();
+    harness.render().unwrap();
+
+    let dump = harness.dump();
+    let root = dump.root().unwrap();
+    let vertical = dump.children(root).next().unwrap();
+    assert_eq!(vertical.name, "vertical");
+    assert_eq!(vertical.parent, Some(root.id));
+
+    let labels = dump.children(vertical).collect::<Vec<_>>();
+    assert_eq!(labels.len(), 2);
+    assert!(labels.iter().all(|label| label.name == "label"));
+    assert_ne!(labels[0].params, labels[1].params);
+
+    let json = dump.to_json();
+    assert_eq!(TreeDump::from_json(&json).unwrap(), dump);
+    assert!(TreeDump::from_json("{}").is_err());
+}
@@ -0,0 +1,10 @@
+use too_lua::{Bindings, generate};
+
+#[test]
+fn value_operators_need_the_value_proxy() {
+    let annotations = generate(&Bindings::default_bindings());
+    assert!(annotations.contains("---@operator add(number|Value): number"));
+
+    let annotations = generate(&Bindings::default());
+    assert!(!annotations.contains("---@class Value"));
+}
@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use too::{backend::Event, math::vec2};
+use too_lua::{Headless, Input, bindings::Value};
+
+#[test]
+fn render_label() {
+    let mut harness = Headless::from_source(
+        r#"
+        return function(ui)
+            ui.label "hello world"
+        end
+        "#,
+    )
+    .size(vec2(20, 3))
+    .start()
+    .unwrap();
+
+    let snapshot = harness.render().unwrap();
+    assert_eq!(snapshot.size(), vec2(20, 3));
+    assert!(snapshot.text().contains("hello world"));
+}
+
+#[test]
+fn replay_input() {
+    let mut harness = Headless::from_source(
+        r#"
+        local width = Value.persist("width", 0)
+        return {
+            on_resize = function(w, h)
+                width.value = w
+            end,
+            view = function(ui)
+                ui.label "hello"
+            end,
+        }
+        "#,
+    )
+    .start()
+    .unwrap();
+
+    assert_eq!(harness.value("width"), Some(Value::Signed(80)));
+
+    let inputs = [
+        Input::from(Event::Resize(vec2(40, 10))),
+        Input::from(Duration::from_millis(16)),
+    ];
+    let mut steps = vec![];
+    harness
+        .replay(inputs, |i, harness| {
+            steps.push((i, harness.snapshot().size()));
+        })
+        .unwrap();
+
+    assert_eq!(steps, [(0, vec2(40, 10)), (1, vec2(40, 10))]);
+    assert_eq!(harness.value("width"), Some(Value::Signed(40)));
+    assert!(harness.take_errors().is_empty());
+}
+
+#[test]
+fn spawn_tasks() {
+    let mut harness = Headless::from_source(
+        r#"
+        local done = Value.persist("done", false)
+        return {
+            init = function()
+                Runtime.spawn(function()
+                    done.value = true
+                end)
+            end,
+            view = function(ui)
+                ui.label "hello"
+            end,
+        }
+        "#,
+    )
+    .start()
+    .unwrap();
+
+    assert_eq!(harness.value("done"), Some(Value::Bool(false)));
+    harness.render().unwrap();
+    assert_eq!(harness.value("done"), Some(Value::Bool(true)));
+
+    let guard = harness.enter();
+    let id = harness
+        .lua()
+        .load("return Runtime.spawn(function() return 42 end)")
+        .eval::<u64>()
+        .unwrap();
+    drop(guard);
+
+    harness.render().unwrap();
+    let status = harness
+        .lua()
+        .load(format!("return Runtime.status({id})"))
+        .eval::<String>()
+        .unwrap();
+    assert_eq!(status, "finished");
+    assert!(harness.take_errors().is_empty());
+}
+
+#[test]
+fn elapsed_time_drives_timers() {
+    let mut harness = Headless::from_source(
+        r#"
+        local fired = Value.persist("fired", false)
+        local ticks = Value.persist("ticks", 0)
+        return {
+            init = function()
+                Runtime.after(Duration.from_secs(1), function()
+                    fired.value = true
+                end)
+                Runtime.interval(Duration.from_millis(100), function()
+                    ticks.value = ticks.value + 1
+                end)
+            end,
+            view = function(ui)
+                ui.label "hello"
+            end,
+        }
+        "#,
+    )
+    .start()
+    .unwrap();
+
+    harness.input(Duration::from_millis(500)).unwrap();
+    assert_eq!(harness.value("fired"), Some(Value::Bool(false)));
+    assert_eq!(harness.value("ticks"), Some(Value::Signed(1)));
+
+    harness.input(Duration::from_millis(500)).unwrap();
+    assert_eq!(harness.value("fired"), Some(Value::Bool(true)));
+    assert_eq!(harness.value("ticks"), Some(Value::Signed(2)));
+    assert!(harness.take_errors().is_empty());
+}
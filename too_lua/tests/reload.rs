@@ -0,0 +1,139 @@
+mod common;
+
+use common::SharedLoader;
+use too_lua::{Headless, bindings::Value};
+
+#[test]
+fn failed_reload_keeps_previous_tree() {
+    let loader = SharedLoader::new(
+        r#"
+        return function(ui)
+            ui.label "first"
+        end
+        "#,
+    );
+    let mut harness = Headless::from_loader(loader.clone()).start().unwrap();
+    assert!(harness.render().unwrap().text().contains("first"));
+
+    loader.set(
+        r#"
+        return function(ui)
+            ui.label "partial"
+            error("boom")
+        end
+        "#,
+    );
+    assert!(harness.reload().is_err());
+    let text = harness.render().unwrap().text();
+    assert!(text.contains("first"));
+    assert!(!text.contains("partial"));
+
+    loader.set("return function(ui) ui.label( ");
+    assert!(harness.reload().is_err());
+    assert!(harness.render().unwrap().text().contains("first"));
+
+    loader.set(
+        r#"
+        return function(ui)
+            ui.label "second"
+        end
+        "#,
+    );
+    harness.reload().unwrap();
+    assert!(harness.render().unwrap().text().contains("second"));
+}
+
+#[test]
+fn reload_hands_saved_state_to_the_new_script() {
+    let loader = SharedLoader::new(
+        r#"
+        local count = Value.persist("count", 0)
+        local inited = Value.persist("inited", 0)
+        return {
+            version = 1,
+            init = function()
+                inited.value = inited.value + 1
+            end,
+            save = function()
+                return { count = count.value * 10 }
+            end,
+            view = function(ui)
+                ui.label "first"
+            end,
+        }
+        "#,
+    );
+    let mut harness = Headless::from_loader(loader.clone()).start().unwrap();
+    assert!(harness.set_value("count", 4_isize).unwrap());
+
+    loader.set(
+        r#"
+        local restored = Value.persist("restored", 0)
+        local version = Value.persist("version", 0)
+        return {
+            version = 2,
+            init = function()
+                error "init only runs on the first load"
+            end,
+            on_reload = function(state, previous)
+                restored.value = state.count
+                version.value = previous
+            end,
+            save = function()
+                error "broken save"
+            end,
+            view = function(ui)
+                ui.label "second"
+            end,
+        }
+        "#,
+    );
+    harness.reload().unwrap();
+    assert_eq!(harness.value("inited"), Some(Value::Signed(1)));
+    assert_eq!(harness.value("restored"), Some(Value::Signed(40)));
+    assert_eq!(harness.value("version"), Some(Value::Signed(1)));
+
+    // a failing save is reported, and the next script gets nil
+    loader.set(
+        r#"
+        local state = Value.persist("state", "")
+        return {
+            on_reload = function(saved, previous)
+                state.value = tostring(saved) .. " " .. tostring(previous)
+            end,
+            view = function(ui)
+                ui.label "third"
+            end,
+        }
+        "#,
+    );
+    harness.reload().unwrap();
+    assert!(harness.render().unwrap().text().contains("third"));
+    assert_eq!(harness.value("state"), Some(Value::String("nil 2".into())));
+    let errors = harness.take_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].to_string().contains("broken save"));
+}
+
+#[test]
+fn reload_reports_the_size_to_the_new_script() {
+    let loader = SharedLoader::new("return function(ui) end");
+    let mut harness = Headless::from_loader(loader.clone())
+        .size(too::math::vec2(40, 10))
+        .start()
+        .unwrap();
+
+    loader.set(
+        r#"
+        local size = Value.persist("size", "")
+        return {
+            on_resize = function(w, h)
+                size.value = w .. "x" .. h
+            end,
+            view = function(ui) end,
+        }
+        "#,
+    );
+    harness.reload().unwrap();
+    assert_eq!(harness.value("size"), Some(Value::String("40x10".into())));
+}
@@ -0,0 +1,21 @@
+use too_lua::Headless;
+
+#[test]
+fn require_loaded_modules_without_resolving_them() {
+    let harness = Headless::from_source(
+        r#"
+        package.loaded.custom = { answer = 42 }
+        package.preload.preloaded = function() return { answer = 7 } end
+        -- a broken `package.path` isn't resolved for modules that are already loaded
+        package.path = false
+        assert(require("custom").answer == 42)
+        assert(require("string") == string)
+
+        -- and for the rest, it isn't a reason to not load them
+        assert(require("preloaded").answer == 7)
+        return function(ui) end
+        "#,
+    )
+    .start();
+    assert!(harness.is_ok(), "{:?}", harness.err());
+}
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use too_lua::{Harness, Headless, bindings::Value};
+
+fn start() -> Harness {
+    Headless::from_source("return function(ui) ui.label 'tasks' end")
+        .start()
+        .unwrap()
+}
+
+fn run<R: mlua::FromLuaMulti>(harness: &Harness, source: &str) -> R {
+    let _guard = harness.enter();
+    harness.lua().load(source).eval().unwrap()
+}
+
+#[test]
+fn timers_fire_after_their_delay() {
+    let mut harness = start();
+    let interval: u64 = run(
+        &harness,
+        r#"
+        local once = Value.persist("once", 0)
+        local ticks = Value.persist("ticks", 0)
+        Runtime.after(Duration.from_millis(250), function()
+            once.value = once.value + 1
+        end)
+        return Runtime.interval(Duration.from_millis(100), function()
+            ticks.value = ticks.value + 1
+        end)
+        "#,
+    );
+
+    harness.input(Duration::from_millis(50)).unwrap();
+    assert_eq!(harness.value("ticks"), Some(Value::Signed(0)));
+
+    for _ in 0..3 {
+        harness.input(Duration::from_millis(100)).unwrap();
+    }
+    assert_eq!(harness.value("once"), Some(Value::Signed(1)));
+    assert_eq!(harness.value("ticks"), Some(Value::Signed(3)));
+
+    // `after` only fires once, and a stopped interval doesn't fire again
+    assert!(run::<bool>(
+        &harness,
+        &format!("return Runtime.stop({interval})")
+    ));
+    assert!(!run::<bool>(
+        &harness,
+        &format!("return Runtime.stop({interval})")
+    ));
+    for _ in 0..3 {
+        harness.input(Duration::from_millis(100)).unwrap();
+    }
+    assert_eq!(harness.value("once"), Some(Value::Signed(1)));
+    assert_eq!(harness.value("ticks"), Some(Value::Signed(3)));
+    assert!(harness.take_errors().is_empty());
+}
+
+#[test]
+fn task_status_and_join() {
+    let mut harness = start();
+    let status = |harness: &Harness, id: u64| -> Option<String> {
+        run(harness, &format!("return Runtime.status({id})"))
+    };
+
+    let (quick, failing, slow): (u64, u64, u64) = run(
+        &harness,
+        r#"
+        return
+            Runtime.spawn(function() return 42 end),
+            Runtime.spawn(function() error "boom" end),
+            Runtime.spawn(function() Runtime.sleep(Duration.from_secs(60)) end)
+        "#,
+    );
+    assert_eq!(status(&harness, quick).as_deref(), Some("running"));
+
+    harness.render().unwrap();
+    assert_eq!(status(&harness, quick).as_deref(), Some("finished"));
+    assert_eq!(status(&harness, failing).as_deref(), Some("failed"));
+    assert_eq!(status(&harness, slow).as_deref(), Some("running"));
+
+    let errors = harness.take_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].to_string().contains("boom"));
+
+    assert!(run::<bool>(
+        &harness,
+        &format!("return Runtime.stop({slow})")
+    ));
+    harness.render().unwrap();
+    assert_eq!(status(&harness, slow).as_deref(), Some("stopped"));
+
+    let _: () = run(
+        &harness,
+        &format!(
+            r#"
+            local joined = Value.persist("joined", 0)
+            local rejoined = Value.persist("rejoined", "")
+            local failed = Value.persist("failed", "")
+            Runtime.spawn(function()
+                joined.value = Runtime.join({quick})
+                -- a joined task is forgotten
+                local ok, err = pcall(Runtime.join, {quick})
+                rejoined.value = tostring(err)
+                local ok, err = pcall(Runtime.join, {failing})
+                failed.value = tostring(err)
+            end)
+            "#
+        ),
+    );
+    harness.render().unwrap();
+    harness.render().unwrap();
+
+    assert_eq!(harness.value("joined"), Some(Value::Signed(42)));
+    assert_eq!(status(&harness, quick), None);
+    let rejoined = harness.value("rejoined").unwrap().to_string();
+    assert!(rejoined.contains("unknown task"));
+    let failed = harness.value("failed").unwrap().to_string();
+    assert!(failed.contains("boom"));
+    assert!(harness.take_errors().is_empty());
+}
@@ -0,0 +1,77 @@
+use mlua::StdLib;
+use too_lua::{Harness, Headless, Sandbox};
+
+fn start(sandbox: Sandbox) -> Harness {
+    Headless::from_source(
+        r#"
+        return function(ui)
+            ui.label "sandboxed"
+        end
+        "#,
+    )
+    .sandbox(sandbox)
+    .start()
+    .unwrap()
+}
+
+fn fails(harness: &Harness, source: &str) -> bool {
+    harness.lua().load(source).exec().is_err()
+}
+
+#[test]
+fn os_is_stripped() {
+    let harness = start(Sandbox::new().allow_std(StdLib::OS));
+    assert!(!fails(&harness, "assert(os.time())"));
+    assert!(fails(&harness, "os.execute('true')"));
+    assert!(fails(&harness, "os.remove('file')"));
+}
+
+#[test]
+fn io_is_not_available() {
+    let harness = start(Sandbox::new());
+    assert!(fails(&harness, "io.open('file')"));
+
+    let result = Headless::from_source("return function(ui) end")
+        .sandbox(Sandbox::new().allow_std(StdLib::IO))
+        .start();
+    assert!(result.is_err());
+}
+
+#[test]
+fn process_and_fs_have_to_be_allowed() {
+    let harness = start(Sandbox::new());
+    assert!(fails(&harness, "Process.spawn('true')"));
+    assert!(fails(&harness, "Fs.read('file')"));
+    assert!(!fails(&harness, "assert(Value.new(1))"));
+
+    let harness = start(Sandbox::new().allow_proxies(["Process"]));
+    assert!(!fails(&harness, "assert(Process)"));
+    assert!(fails(&harness, "assert(Fs)"));
+}
+
+#[test]
+fn require_only_searches_its_path() {
+    let root = std::env::temp_dir().join(format!("too_lua_sandbox_{}", std::process::id()));
+    let inside = root.join("inside");
+    std::fs::create_dir_all(&inside).unwrap();
+    std::fs::write(inside.join("allowed.lua"), "return 42").unwrap();
+    std::fs::write(root.join("secret.lua"), "return 'secret'").unwrap();
+
+    let path = format!("{}/?.lua", inside.display());
+    let harness = start(Sandbox::new().require_path(path));
+    assert!(!fails(&harness, "assert(require 'allowed' == 42)"));
+    assert!(fails(&harness, "require 'secret'"));
+    assert!(fails(&harness, "require '../secret'"));
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn memory_limit_is_enforced() {
+    let harness = start(Sandbox::new().memory_limit(8 * 1024 * 1024));
+    assert!(!fails(&harness, "local s = string.rep('x', 1024)"));
+    assert!(fails(
+        &harness,
+        "local s = string.rep('x', 16 * 1024 * 1024)"
+    ));
+}
@@ -0,0 +1,246 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::SharedLoader;
+use too_lua::{Headless, bindings::Value};
+
+#[test]
+fn value_write_rebuilds_reactive_nodes() {
+    let mut harness = Headless::from_source(
+        r#"
+        local count = Value.persist("count", 1)
+        return function(ui)
+            ui.label "static"
+            reactive(function(ui)
+                ui.label("count: " .. tostring(count.value))
+            end)
+        end
+        "#,
+    )
+    .start()
+    .unwrap();
+
+    let text = harness.render().unwrap().text();
+    assert!(text.contains("count: 1"));
+    let nodes = harness.dump().nodes.len();
+
+    for i in 2..10_isize {
+        assert!(harness.set_value("count", i).unwrap());
+        let text = harness.render().unwrap().text();
+        assert!(text.contains(&format!("count: {i}")));
+        assert!(text.contains("static"));
+    }
+
+    // rebuilding replaces the old nodes rather than adding to them
+    assert_eq!(harness.dump().nodes.len(), nodes);
+    assert!(harness.take_errors().is_empty());
+}
+
+#[test]
+fn failing_reactive_nodes_do_not_stop_the_others() {
+    let mut harness = Headless::from_source(
+        r#"
+        local count = Value.persist("count", 1)
+        return function(ui)
+            for i = 1, 3 do
+                reactive(function(ui)
+                    if count.value > 1 and i ~= 2 then
+                        error("broken " .. i)
+                    end
+                    ui.label("node " .. i .. ": " .. tostring(count.value))
+                end)
+            end
+        end
+        "#,
+    )
+    .start()
+    .unwrap();
+
+    harness.render().unwrap();
+    assert!(harness.set_value("count", 2).unwrap());
+    let text = harness.render().unwrap().text();
+    assert!(text.contains("node 2: 2"));
+
+    let errors = harness.take_errors();
+    assert_eq!(errors.len(), 2);
+    for name in ["broken 1", "broken 3"] {
+        assert!(errors.iter().any(|err| err.to_string().contains(name)));
+    }
+}
+
+#[test]
+fn failing_computed_values_do_not_stop_the_others() {
+    let mut harness = Headless::from_source(
+        r#"
+        local count = Value.persist("count", 1)
+        local broken = Value.computed(function()
+            if count.value > 1 then
+                error "broken"
+            end
+            return count.value
+        end)
+        local doubled = Value.computed(function()
+            return count.value * 2
+        end)
+        return function(ui)
+            reactive(function(ui)
+                ui.label("doubled: " .. tostring(doubled.value))
+            end)
+        end
+        "#,
+    )
+    .start()
+    .unwrap();
+
+    harness.render().unwrap();
+    assert!(harness.set_value("count", 2).unwrap());
+    let text = harness.render().unwrap().text();
+    assert!(text.contains("doubled: 4"));
+
+    let errors = harness.take_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].to_string().contains("broken"));
+}
+
+#[test]
+fn value_changes_notify_subscribers() {
+    let source = r#"
+        local count = Value.persist("count", 1)
+        local calls = Value.persist("calls", 0)
+        local stopped = Value.persist("stopped", 0)
+
+        count:on_change(function(old, new)
+            calls.value = calls.value + 1
+        end)
+        local id = count:on_change(function(old, new)
+            stopped.value = new
+        end)
+        assert(Runtime.stop(id))
+
+        return function(ui)
+            ui.label "hello"
+        end
+    "#;
+
+    let changes = Arc::new(Mutex::new(vec![]));
+    let loader = SharedLoader::new(source);
+    let mut harness = Headless::from_loader(loader.clone())
+        .on_value_change("count", {
+            let changes = Arc::clone(&changes);
+            move |old, new| changes.lock().unwrap().push((old.clone(), new.clone()))
+        })
+        .start()
+        .unwrap();
+    harness.render().unwrap();
+
+    assert!(harness.set_value("count", 5_isize).unwrap());
+    harness.render().unwrap();
+    assert_eq!(harness.value("calls"), Some(Value::Signed(1)));
+    assert_eq!(harness.value("stopped"), Some(Value::Signed(0)));
+    assert_eq!(
+        *changes.lock().unwrap(),
+        [(Value::Signed(1), Value::Signed(5))]
+    );
+
+    // the new script subscribes again, replacing the old script's subscription
+    harness.reload().unwrap();
+    assert!(harness.set_value("count", 6_isize).unwrap());
+    harness.render().unwrap();
+    assert_eq!(harness.value("calls"), Some(Value::Signed(2)));
+    assert_eq!(changes.lock().unwrap().len(), 2);
+    assert!(harness.take_errors().is_empty());
+}
+
+#[test]
+fn value_operators_and_helpers() {
+    let harness = Headless::from_source("return function(ui) end")
+        .start()
+        .unwrap();
+
+    harness
+        .lua()
+        .load(
+            r#"
+            local a, b = Value.new(3), Value.new(4)
+            assert(a + b == 7)
+            assert(a + 1.5 == 4.5)
+            assert(10 - a == 7)
+            assert(a * b == 12)
+            assert(b / 2 == 2)
+            assert(-a == -3)
+            assert(a < b and a <= b and not (b < a))
+            assert(a < 4 and 2 < a)
+            assert(a == Value.new(3) and a ~= b)
+            assert(tostring(a) == "3")
+            assert("a=" .. a == "a=3" and a .. "!" == "3!")
+
+            -- integers saturate, for operators and `inc` alike
+            local big = Value.new(math.maxinteger)
+            assert(big + 1 == math.maxinteger)
+            assert(big:inc() == math.maxinteger)
+
+            local flag = Value.new(false)
+            assert(flag:toggle() == true and flag.value == true)
+
+            local n = Value.new(15)
+            assert(n:inc(-2) == 13)
+            assert(n:clamp(0, 10) == 10 and n.value == 10)
+            assert(not pcall(n.clamp, n, 10, 0))
+
+            local f = Value.new(1.5)
+            assert(f:inc(0.5) == 2)
+            "#,
+        )
+        .exec()
+        .unwrap();
+}
+
+#[test]
+fn list_and_map_values() {
+    let harness = Headless::from_source("return function(ui) end")
+        .start()
+        .unwrap();
+
+    harness
+        .lua()
+        .load(
+            r#"
+            local list = Value.list { "a", "b" }
+            assert(#list == 2 and list[1] == "a" and list[3] == nil)
+
+            list:insert("c")
+            list:insert(1, "z")
+            list[5] = "d"
+            assert(#list == 5 and list[1] == "z" and list[5] == "d")
+            assert(list:remove(1) == "z" and list:remove(10) == nil)
+            assert(not pcall(function() list[10] = "x" end))
+            assert(not pcall(function() return list.key end))
+
+            local items = {}
+            for i, v in pairs(list) do
+                items[#items + 1] = i .. "=" .. v
+            end
+            assert(table.concat(items, ",") == "1=a,2=b,3=c,4=d")
+
+            local map = Value.map { a = 1 }
+            map:insert("b", 2)
+            local ok, err = pcall(map.insert, map, "x")
+            assert(not ok and tostring(err):find("needs a key and a value"))
+            map["c"] = { 1, 2 }
+            assert(map.a == 1 and map["c"][2] == 2 and #map == 3)
+            assert(map:remove("a") == 1 and map.a == nil)
+            map["b"] = nil
+            assert(map.b == nil)
+            assert(not pcall(function() return map[1] end))
+
+            local keys = {}
+            for k in pairs(map) do
+                keys[#keys + 1] = k
+            end
+            assert(table.concat(keys, ",") == "c")
+            "#,
+        )
+        .exec()
+        .unwrap();
+}
@@ -1,17 +1,22 @@
-use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 
-use too_lua::Bindings;
+use too_lua::{Bindings, Headless};
 
 fn main() -> std::io::Result<()> {
     let task = std::env::args().nth(1);
-    let path = std::env::args()
-        .nth(2)
-        .map(Cow::Owned)
-        .unwrap_or(Cow::Borrowed("_.lua"));
+    let args = std::env::args()
+        .skip(2)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+    let update = std::env::args().any(|arg| arg == "--update");
 
     match task.as_deref() {
         Some("generate") => {
-            generate(&path)?;
+            generate(args.first().map(String::as_str).unwrap_or("_.lua"))?;
+        }
+        Some("snapshot") => {
+            let dir = args.first().map(String::as_str);
+            snapshot(dir.unwrap_or("too_lua/examples"), update)?;
         }
         _ => eprintln!("{HELP}"),
     }
@@ -20,8 +25,110 @@ fn main() -> std::io::Result<()> {
 }
 
 static HELP: &str = "Tasks:
-    generate <file_name?>   generates lua annotations";
+    generate <file_name?>   generates lua annotations
+    snapshot <dir?>         renders every lua script in a directory and compares it to its .snap file
+        --update            overwrites the .snap files with the new output";
+
+const SNAPSHOT_WIDTH: i32 = 80;
+const SNAPSHOT_HEIGHT: i32 = 24;
 
 fn generate(path: &str) -> std::io::Result<()> {
     too_lua::write_annotations(path, &Bindings::default_bindings())
 }
+
+fn snapshot(dir: &str, update: bool) -> std::io::Result<()> {
+    let mut scripts = vec![];
+    find_scripts(Path::new(dir), &mut scripts)?;
+    scripts.sort();
+
+    let mut failed = 0;
+    for script in &scripts {
+        let snap = script.with_extension("snap");
+
+        let output = match render(script) {
+            Ok(output) => output,
+            Err(err) => {
+                eprintln!("FAIL {path}: {err}", path = script.display());
+                failed += 1;
+                continue;
+            }
+        };
+
+        let expected = std::fs::read_to_string(&snap).ok();
+        match expected {
+            Some(expected) if expected == output => {
+                eprintln!("ok   {path}", path = script.display());
+            }
+            Some(expected) if !update => {
+                eprintln!("FAIL {path}: output changed", path = script.display());
+                print_diff(&expected, &output);
+                failed += 1;
+            }
+            _ => {
+                std::fs::write(&snap, &output)?;
+                eprintln!("new  {path}", path = snap.display());
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(std::io::Error::other(format!(
+            "{failed} of {total} snapshots failed",
+            total = scripts.len()
+        )));
+    }
+
+    Ok(())
+}
+
+// the harness has its own runtime, so screens that spawn tasks can be rendered too
+fn render(script: &Path) -> std::io::Result<String> {
+    // `require` searches relative to the working directory,
+    // so this renders the script as if it was run from its own directory
+    let script = std::path::absolute(script)?;
+    let previous = std::env::current_dir()?;
+    if let Some(dir) = script.parent() {
+        std::env::set_current_dir(dir)?;
+    }
+
+    let snapshot = Headless::new(&script)
+        .size(too::math::vec2(SNAPSHOT_WIDTH, SNAPSHOT_HEIGHT))
+        .start()
+        .map_err(|err| std::io::Error::other(err.to_string()))
+        .and_then(|mut harness| Ok(harness.render()?.text()));
+
+    std::env::set_current_dir(previous)?;
+    snapshot
+}
+
+fn find_scripts(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_scripts(&path, out)?;
+        } else if path.extension().is_some_and(|ext| ext == "lua") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn print_diff(expected: &str, actual: &str) {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(left), Some(right)) if left == right => {}
+            (left, right) => {
+                eprintln!("  line {line}:", line = i + 1);
+                if let Some(left) = left {
+                    eprintln!("    - {left}");
+                }
+                if let Some(right) = right {
+                    eprintln!("    + {right}");
+                }
+            }
+        }
+    }
+}